@@ -0,0 +1,125 @@
+//! Headless replay verifier.
+//!
+//! Plays back a replay file without opening any windows and reports the final state hash, the
+//! duration of the replay, the time of the run if it finished, whether it was a practice run and
+//! whether the checksums embedded in the replay matched. Suitable for validating runs on machines
+//! with no display.
+//!
+//! Usage: `verify_replay <replay> [<level>]`
+//!
+//! If _level_ is given, it is used as the level reference instead of the one stored in the replay.
+//!
+//! Exits with status 0 if all checksums matched, 1 if a desync was detected and 2 if the replay or
+//! the level could not be loaded.
+
+use std::{process::ExitCode, time::Instant};
+
+use trapiron::{
+    crash,
+    logic::Logic,
+    world::{
+        Event,
+        format::LoadError,
+        hash::StateHash,
+        replay::{self, Player, Replay},
+        target_tick_duration,
+    },
+};
+
+/// Load the replay from file at _path_.
+fn load_replay(path: &str) -> Result<Replay, LoadError> {
+    let file = std::fs::File::open(path)?;
+    Replay::load(&mut std::io::BufReader::new(file))
+}
+
+fn main() -> ExitCode {
+    crash::setup_panic_hook();
+
+    let args: Vec<String> = std::env::args().collect();
+    let (replay_path, level_override) = match args.as_slice() {
+        [_, replay] => (replay, None),
+        [_, replay, level] => (replay, Some(level)),
+        _ => {
+            eprintln!("Usage: verify_replay <replay> [<level>]");
+            return ExitCode::from(2);
+        }
+    };
+
+    let replay = match load_replay(replay_path) {
+        Ok(replay) => replay,
+        Err(error) => {
+            eprintln!("Could not load replay {replay_path:?}: {error}");
+            return ExitCode::from(2);
+        }
+    };
+
+    let level_reference = level_override.unwrap_or(&replay.level);
+    let level = match replay::load_level(level_reference) {
+        Ok(level) => level,
+        Err(error) => {
+            eprintln!("Could not load level {level_reference:?}: {error}");
+            return ExitCode::from(2);
+        }
+    };
+
+    let started = Instant::now();
+    let logic = Logic::new();
+    let mut player = Player::with_level(&replay, level);
+    let mut desyncs = 0;
+
+    crash::with_context(("Replay", || replay_path), || {
+        loop {
+            match player.step(&logic) {
+                Ok(Some(_)) => {}
+                Ok(None) => break,
+                Err(desync) => {
+                    eprintln!("{desync}");
+                    desyncs += 1;
+                }
+            }
+        }
+    });
+
+    let logic_ticks = replay
+        .events
+        .iter()
+        .filter(|event| matches!(event, Event::LogicTick))
+        .count() as u32;
+
+    let run = &player.world().run;
+    let run_time = match run.final_time() {
+        Some(time) => format!(
+            "{:.3} s ({} logic ticks)",
+            time.as_secs_f64(),
+            run.logic_ticks
+        ),
+        None => "not finished".to_string(),
+    };
+    let run_kind = if run.practice {
+        "practice run, not eligible as a record"
+    } else {
+        "record attempt"
+    };
+
+    println!("level:      {level_reference}");
+    println!("events:     {}", replay.events.len());
+    println!(
+        "duration:   {:.3} s ({logic_ticks} logic ticks)",
+        (target_tick_duration() * logic_ticks).as_secs_f64()
+    );
+    println!("run time:   {run_time}");
+    println!("run kind:   {run_kind}");
+    println!(
+        "checksums:  {}/{} matched",
+        player.checksums_verified() - desyncs,
+        replay.checksums.len()
+    );
+    println!("state hash: {:016x}", player.world().checksum());
+    println!("verified in {:.3} s", started.elapsed().as_secs_f64());
+
+    if desyncs == 0 {
+        ExitCode::SUCCESS
+    } else {
+        ExitCode::FAILURE
+    }
+}
@@ -2,22 +2,60 @@
 //!
 //! Compare [`crate::world`] and [`crate::logic`] that can be used headless.
 
+pub mod block;
 mod control;
 mod view;
 
 use std::{
+    io::Write,
+    path::PathBuf,
     rc::Rc,
-    time::{Duration, Instant},
+    time::{Duration, Instant, SystemTime},
 };
 
 use crate::{
     client::{control::Control, view::View},
-    content::Resources,
     gui::{Dcf, Drawable},
     logic::Logic,
-    world::{Event, World},
+    world::{
+        Event, Level, World,
+        format::LoadError,
+        replay::{self, Replay},
+        run::RunState,
+        savestate::Savestate,
+    },
 };
 
+/// Directory that replays of play sessions are written to, relative to the working directory.
+pub const REPLAY_DIRECTORY: &str = "replays";
+
+/// Directory that the savestate used for practice is written to, relative to the working directory.
+pub const SAVESTATE_DIRECTORY: &str = "savestates";
+
+/// Maximum number of events recorded in a single session.
+///
+/// Once a replay reaches this length it is saved and recording stops, so that the recording of a
+/// long session does not grow without bounds. This is several hours of play at usual frame rates.
+pub const MAX_REPLAY_EVENTS: usize = 1 << 20;
+
+/// All resources required to present content, such as textures and models, as well as the
+/// registry of all known block kinds.
+///
+/// This struct should be initialized once when game first loads, as [`Self::new`] is rather
+/// expensive.
+pub struct Resources {
+    pub blocks: block::Kinds,
+}
+
+impl Resources {
+    /// Initializes all runtime resources content needs: loads textures, generates models, etc.
+    pub fn new(gui: &mut crate::gui::Gui) -> Self {
+        Self {
+            blocks: block::Kinds::new(gui),
+        }
+    }
+}
+
 /// Generalized statistics tracker for the two types of regular update routines: logic ticks and
 /// presentation ticks.
 ///
@@ -69,6 +107,16 @@ pub struct Game {
     control: Control,
     logic: Logic,
 
+    /// Recording of all events processed by `world` so far, unless `recording` is `false`.
+    replay: Replay,
+
+    /// The file in [`REPLAY_DIRECTORY`] that `replay` is saved to.
+    replay_path: PathBuf,
+
+    /// Whether new events are still added to `replay`. Cleared once it reaches
+    /// [`MAX_REPLAY_EVENTS`].
+    recording: bool,
+
     resources: Rc<Resources>,
 
     logic_ticks: TickStats,
@@ -79,7 +127,7 @@ impl Game {
     /// tmp: should accept World and Logic externally probably
     pub fn new(resources: Rc<Resources>, gui: &mut crate::gui::Gui) -> Self {
         Self {
-            world: World::new(&resources),
+            world: World::from_levels(vec![Level::test()]),
             view: View::new(gui),
             view_settings: view::Parameters {
                 camera: view::Camera::PlayerCharacter,
@@ -87,6 +135,9 @@ impl Game {
             },
             control: Control::new(),
             logic: Logic::new(),
+            replay: Replay::new(replay::TEST_LEVEL),
+            replay_path: replay_path(),
+            recording: true,
 
             resources,
 
@@ -126,8 +177,18 @@ impl Game {
     fn tick_logic(&mut self, now: Instant) {
         crate::crash::with_context(("Tick phase", || "logic"), || {
             self.logic_ticks.start_tick(now);
+            self.record(&Event::LogicTick);
             self.world.process(Event::LogicTick, &self.logic);
             self.logic_ticks.end_tick(now);
+
+            if self.recording
+                && self
+                    .logic_ticks
+                    .completed
+                    .is_multiple_of(replay::CHECKSUM_INTERVAL as u64)
+            {
+                self.replay.record_checksum(&self.world);
+            }
         });
     }
 
@@ -137,31 +198,171 @@ impl Game {
             self.presentation_ticks.start_tick(now);
 
             let presentation_tick = Event::PresentationTick {
-                duration: self.presentation_ticks.last_duration,
+                duration: (self.presentation_ticks.last_duration)
+                    .min(crate::world::MAX_PRESENTATION_TICK_DURATION),
             };
 
-            for event in self.control.pending_events().chain([presentation_tick]) {
+            let events: Vec<Event> = self
+                .control
+                .pending_events()
+                .chain([presentation_tick])
+                .collect();
+            for event in events {
+                let respawn = matches!(
+                    event,
+                    Event::RespawnPlayerCharacter | Event::Restart | Event::LoadSavestate { .. }
+                );
+                let finished = self.world.run.state == RunState::Finished;
+                self.record(&event);
                 self.world.process(event, &self.logic);
+                if respawn {
+                    self.control.sync_rotation(&self.world);
+                }
+                if !finished && self.world.run.state == RunState::Finished && self.recording {
+                    self.save_replay();
+                }
+            }
+
+            // Respawn dead characters immediately; the request is processed in the next tick
+            if !self.world.player.alive {
+                self.control.request_respawn();
             }
 
             self.presentation_ticks.end_tick(now);
         });
     }
 
+    /// Get the recording of this session so far.
+    pub fn replay(&self) -> &Replay {
+        &self.replay
+    }
+
+    /// Add _event_ to the replay unless recording has stopped.
+    ///
+    /// Saves the replay and stops recording once the replay reaches [`MAX_REPLAY_EVENTS`].
+    fn record(&mut self, event: &Event) {
+        if !self.recording {
+            return;
+        }
+
+        if self.replay.events.len() >= MAX_REPLAY_EVENTS {
+            println!("Replay is too long, recording stopped");
+            self.save_replay();
+            self.recording = false;
+            return;
+        }
+
+        self.replay.record(event);
+    }
+
+    /// Write the replay to its file, replacing the version saved previously.
+    ///
+    /// Errors are reported to stderr; they should not interrupt the game.
+    fn save_replay(&self) {
+        let path = &self.replay_path;
+        let result = std::fs::create_dir_all(REPLAY_DIRECTORY).and_then(|()| {
+            let mut output = std::io::BufWriter::new(std::fs::File::create(path)?);
+            self.replay.save(&mut output)?;
+            output.flush()
+        });
+
+        match result {
+            Ok(()) => println!("Saved replay to {}", path.display()),
+            Err(error) => eprintln!("Could not save replay to {}: {error}", path.display()),
+        }
+    }
+
     /// React to GUI input.
     pub fn on_input(&mut self, input: crate::gui::Input, gui: &mut crate::gui::Gui) {
         self.control.on_input(input, gui, &self.world);
     }
 }
 
+impl Drop for Game {
+    fn drop(&mut self) {
+        if self.recording && !self.replay.events.is_empty() {
+            self.save_replay();
+        }
+    }
+}
+
+/// Choose the file in [`REPLAY_DIRECTORY`] for the replay of a session that starts now.
+fn replay_path() -> PathBuf {
+    let timestamp = SystemTime::now()
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs();
+    [REPLAY_DIRECTORY, &format!("{timestamp}.replay")]
+        .iter()
+        .collect()
+}
+
+/// The file in [`SAVESTATE_DIRECTORY`] that holds the savestate used for practice.
+fn savestate_path() -> PathBuf {
+    [SAVESTATE_DIRECTORY, "quicksave.savestate"]
+        .iter()
+        .collect()
+}
+
+/// Write _savestate_ to its file, replacing the one saved previously.
+///
+/// Errors are reported to stderr; they should not interrupt the game.
+fn save_savestate(savestate: &Savestate) {
+    let path = savestate_path();
+    let result = std::fs::create_dir_all(SAVESTATE_DIRECTORY).and_then(|()| {
+        let mut output = std::io::BufWriter::new(std::fs::File::create(&path)?);
+        savestate.save(&mut output)?;
+        output.flush()
+    });
+
+    match result {
+        Ok(()) => println!("Saved savestate to {}", path.display()),
+        Err(error) => eprintln!("Could not save savestate to {}: {error}", path.display()),
+    }
+}
+
+/// Read the savestate last written by [`save_savestate`].
+///
+/// Returns `None` and reports the error to stderr if there is no savestate or it cannot be loaded.
+fn load_savestate() -> Option<Savestate> {
+    let path = savestate_path();
+    let result = std::fs::File::open(&path)
+        .map_err(LoadError::from)
+        .and_then(|file| Savestate::load(&mut std::io::BufReader::new(file)));
+
+    match result {
+        Ok(savestate) => Some(savestate),
+        Err(error) => {
+            eprintln!("Could not load savestate from {}: {error}", path.display());
+            None
+        }
+    }
+}
+
 impl Drawable for Game {
     fn draw(&mut self, dcf: &mut Dcf) {
         crate::crash::with_context(("", || "Game draw"), || {
             self.control.draw(dcf);
             let mut parameters = self.view_settings.clone();
             self.control.tweak_view_parameters(&mut parameters);
-            self.view
-                .draw(dcf, &self.world, &self.resources, &parameters);
+
+            let since_logic_tick = self
+                .logic_ticks
+                .last_timestamp
+                .map_or(Duration::ZERO, |last| {
+                    dcf.time().saturating_duration_since(last)
+                });
+            let tick_progress = (since_logic_tick.as_secs_f32()
+                / crate::world::target_tick_duration().as_secs_f32())
+            .min(1.0);
+
+            self.view.draw(
+                dcf,
+                &self.world,
+                &self.resources,
+                &parameters,
+                tick_progress,
+            );
         });
     }
 }
@@ -0,0 +1,273 @@
+//! Presentation of blocks: GUI resources of block kinds and views of block instances.
+//!
+//! Block state itself is described in [`crate::content::block`], which can be used headless. This
+//! module adds two more types associated with blocks, block [kinds](KindInstance) and block
+//! [views](ViewInstance):
+//! - Block kinds: one per type of block, such as "stone" or "sand"; owns GUI resources.
+//!   - [`KindInstance`]\: trait implemented by every block kind
+//!   - [`Kinds`]\: a registry with one of every known kind
+//!   - [`KindRef`]\: a reference to a kind; essentially just `&dyn KindInstance`
+//! - Block views: look-and-feel model of a specific block with its animation state, etc.
+//!   - [`ViewInstance`]\: trait implemented by every block view
+//!   - [`View`]\: an enum of every possible `ViewInstance` for dispatch.
+//! - [`Presentation`]\: trait that ties every block [`Instance`] to its kind and view types.
+
+mod basic;
+mod pusher;
+mod run;
+mod signal;
+
+use std::rc::Rc;
+
+use crate::{
+    client::Resources,
+    content::block::{
+        Air, Block, Button, Checkpoint, Door, Finish, Hazard, Instance, KindId, Lever, Plate,
+        Pusher, Relay, Sand, Start, Stone, Wire, for_all_blocks,
+    },
+    gui::{Affine3, Drawable, Float, Gui, OpaqueColor, Primitive, Texture, Vec3},
+};
+use basic::*;
+
+/// A single type of block, such as "stone" or "sand".
+///
+/// Responsible for initialization and ownership of resources used by blocks of this kind, such as
+/// textures or models.
+pub trait KindInstance {
+    /// Initialize assets used by this kind.
+    ///
+    /// This is expected to be called once when application starts.
+    fn new(gui: &mut Gui) -> Self;
+}
+
+/// The GUI representation of a specific state of a single block instance.
+///
+/// For now, this only includes its graphics; audio and other kinds of GUI feedback will be included
+/// in the future.
+///
+/// A new view is generated each time the block state updates, so the state of the block can be
+/// baked into the ViewInstance, especially since it doesn't have access to the block state from the
+/// world when it is rendered. This is to facilitate their use outside of world rendering contexts,
+/// such as in UI elements.
+pub trait ViewInstance: Drawable {}
+
+/// The presentation of a block [`Instance`] type.
+///
+/// Every block instance type must implement this trait.
+pub trait Presentation: Instance {
+    /// The kind of this block.
+    type Kind: KindInstance;
+
+    /// The type used for the view of this block.
+    type View: ViewInstance;
+
+    /// Obtain a view for this block state.
+    ///
+    /// This method should execute quickly to avoid lag. Cache all expensive computation in `Kind`;
+    /// for many block kinds, the entire view can be pre-initialized and shared via [`Rc`].
+    fn view(&self, kind: &Self::Kind, rsrc: &Resources) -> Self::View;
+
+    /// Get the displacement of this block from its cell when drawn _tick_progress_ of the way
+    /// from the last logic tick to the next one, between `0.0` and `1.0`.
+    ///
+    /// This interpolates blocks that move between cells, which only happens in logic ticks. Most
+    /// blocks are always drawn in their cell.
+    fn offset(&self, _tick_progress: Float) -> Vec3 {
+        Vec3::ZERO
+    }
+}
+
+/// Texture group of bundled block textures.
+const TEXTURES: crate::gui::TextureGroup = crate::gui::TextureGroup {};
+
+/// A block view that renders an opaque cube.
+///
+/// This view is static, and so it should be pre-initialized in [`KindInstance`].
+#[derive(Clone)]
+pub struct FullCube(Rc<Primitive>);
+
+impl FullCube {
+    /// Create a `FullCube` view with a given texture.
+    fn new(texture: &Rc<Texture>, gui: &mut Gui) -> Self {
+        let mwt = crate::gui::Mesh::tmp_ppp(
+            crate::gui::Vec3::splat(-0.5),
+            crate::gui::Vec3::X,
+            crate::gui::Vec3::Y,
+            crate::gui::Vec3::Z,
+            texture,
+        );
+
+        Self(Rc::new(gui.make_primitive(mwt)))
+    }
+}
+
+impl ViewInstance for FullCube {}
+impl Drawable for FullCube {
+    fn draw(&mut self, dcf: &mut crate::gui::Dcf) {
+        self.0.draw(dcf);
+    }
+}
+
+/// A block view that renders a [`FullCube`] stretched to a box and tinted with a color.
+///
+/// This view is cheap to create, so blocks can build a new one for every state.
+#[derive(Clone)]
+pub struct Cuboid {
+    model: FullCube,
+
+    /// Transform from the unit cube to the box.
+    transform: Affine3,
+
+    color: OpaqueColor,
+}
+
+impl Cuboid {
+    /// Create a `Cuboid` view of _model_ that spans from _min_ to _max_ relative to the center of
+    /// the block and is tinted with _color_.
+    fn new(model: &FullCube, min: Vec3, max: Vec3, color: OpaqueColor) -> Self {
+        Self {
+            model: model.clone(),
+            transform: Affine3::from_translation((min + max) / 2.0)
+                * Affine3::from_scale(max - min),
+            color,
+        }
+    }
+}
+
+impl ViewInstance for Cuboid {}
+impl Drawable for Cuboid {
+    fn draw(&mut self, dcf: &mut crate::gui::Dcf) {
+        self.model
+            .draw(&mut dcf.tfed(self.transform).colored(&self.color));
+    }
+}
+
+/// A kind whose views are [`Cuboid`]s of the stone texture, for blocks without dedicated
+/// textures.
+pub struct PlainKind {
+    model: FullCube,
+}
+
+impl KindInstance for PlainKind {
+    fn new(gui: &mut Gui) -> Self {
+        Self {
+            model: FullCube::new(&gui.texture(&TEXTURES.id("stone")), gui),
+        }
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////////////////////////
+
+/// Generates the presentation registry and boilerplate types for the block kinds listed by
+/// [`for_all_blocks!`].
+macro_rules! all_block_presentations {
+    { $($snake_case:ident: $title_case:ident),+ $(,)? } => {
+        /// A reference to some [`KindInstance`] value from the [registry](Kinds).
+        pub enum KindRef<'a> {
+            $(
+                $title_case(&'a <$title_case as Presentation>::Kind),
+            )*
+        }
+
+        impl<'a> KindRef<'a> {
+            /// Get the stable ID of this kind.
+            pub fn id(&self) -> KindId {
+                match self {
+                    $(
+                        KindRef::$title_case(_) => KindId::from_name(stringify!($snake_case)).unwrap(),
+                    )*
+                }
+            }
+
+            /// Get the name of this kind, as accepted by [`Kinds::get`].
+            pub fn name(&self) -> &'static str {
+                self.id().name()
+            }
+        }
+
+        /// A GUI representation of a specific state of a specific block; a [`ViewInstance`] value.
+        pub enum View {
+            $(
+                $title_case(<$title_case as Presentation>::View),
+            )*
+        }
+
+        impl Drawable for View {
+            fn draw(&mut self, dcf: &mut crate::gui::Dcf) {
+                match self {
+                    $(
+                        View::$title_case(instance) => instance.draw(dcf),
+                    )*
+                }
+            }
+        }
+
+        /// Obtain a view for the state of _block_.
+        ///
+        /// The view will have the state of the block baked into it.
+        pub fn view(block: &Block, rsrc: &Resources) -> View {
+            match block {
+                $(
+                    Block::$title_case(instance) => {
+                        View::$title_case(instance.view(&rsrc.blocks.$snake_case, rsrc))
+                    }
+                )*
+            }
+        }
+
+        /// Get the displacement of _block_ from its cell when drawn _tick_progress_ of the way
+        /// from the last logic tick to the next one. See [`Presentation::offset`].
+        pub fn offset(block: &Block, tick_progress: Float) -> Vec3 {
+            match block {
+                $(
+                    Block::$title_case(instance) => instance.offset(tick_progress),
+                )*
+            }
+        }
+
+        /// All resources required by blocks, such as textures and models, as well as the registry
+        /// of all known block kinds.
+        ///
+        /// This struct should be initialized once when game first loads, as [`Self::new`] is rather
+        /// expensive.
+        pub struct Kinds {
+            $(
+                $snake_case: <$title_case as Presentation>::Kind,
+            )*
+        }
+
+        impl Kinds {
+            /// Initializes all runtime resources content needs: loads textures, generates models,
+            /// etc.
+            pub fn new(gui: &mut crate::gui::Gui) -> Self {
+                Self {
+                    $(
+                        $snake_case: <$title_case as Presentation>::Kind::new(gui),
+                    )*
+                }
+            }
+
+            /// Find a block kind by its name.
+            pub fn get<'a> (&'a self, name: &str) -> Option<KindRef<'a>> {
+                match name {
+                    $(
+                        stringify!($snake_case) => Some(KindRef::$title_case(&self.$snake_case)),
+                    )*
+                    _ => None,
+                }
+            }
+
+            /// Find a block kind by its [ID](KindId).
+            pub fn by_id<'a>(&'a self, id: KindId) -> KindRef<'a> {
+                self.get(id.name()).unwrap()
+            }
+
+            /// Iterate over all known block kinds in the order of their IDs.
+            pub fn iter<'a>(&'a self) -> impl Iterator<Item = KindRef<'a>> {
+                KindId::all().map(|id| self.by_id(id))
+            }
+        }
+    };
+}
+
+for_all_blocks!(all_block_presentations);
@@ -0,0 +1,80 @@
+//! Presentation of block kinds without complicated code.
+
+use crate::client::block::*;
+
+pub struct AirKind;
+
+impl KindInstance for AirKind {
+    fn new(_: &mut Gui) -> Self {
+        Self
+    }
+}
+
+pub struct AirView;
+
+impl ViewInstance for AirView {}
+impl Drawable for AirView {
+    fn draw(&mut self, _: &mut crate::gui::Dcf) {
+        // Do nothing
+    }
+}
+
+impl Presentation for Air {
+    type Kind = AirKind;
+    type View = AirView;
+    fn view(&self, _: &Self::Kind, _: &Resources) -> Self::View {
+        AirView
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////////////////////////
+
+pub struct StoneKind {
+    model: FullCube,
+}
+
+impl KindInstance for StoneKind {
+    fn new(gui: &mut Gui) -> Self {
+        Self {
+            model: FullCube::new(&gui.texture(&TEXTURES.id("stone")), gui),
+        }
+    }
+}
+
+impl Presentation for Stone {
+    type Kind = StoneKind;
+    type View = FullCube;
+    fn view(&self, rsrc: &Self::Kind, _: &Resources) -> Self::View {
+        rsrc.model.clone()
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////////////////////////
+
+pub struct SandKind {
+    model: FullCube,
+}
+
+impl KindInstance for SandKind {
+    fn new(gui: &mut Gui) -> Self {
+        Self {
+            model: FullCube::new(&gui.texture(&TEXTURES.id("sand")), gui),
+        }
+    }
+}
+
+impl Presentation for Sand {
+    type Kind = SandKind;
+    type View = FullCube;
+    fn view(&self, rsrc: &Self::Kind, _: &Resources) -> Self::View {
+        rsrc.model.clone()
+    }
+    fn offset(&self, tick_progress: Float) -> Vec3 {
+        // Falling sand arrived from the cell above in the last logic tick
+        if self.falling {
+            Vec3::Z * (1.0 - tick_progress)
+        } else {
+            Vec3::ZERO
+        }
+    }
+}
@@ -0,0 +1,68 @@
+//! Presentation of [`Pusher`] blocks.
+
+use crate::{client::block::*, content::block::PusherState};
+
+pub struct PusherKind {
+    model_compressed: Rc<Primitive>,
+    model_extended: Rc<Primitive>,
+}
+
+impl KindInstance for PusherKind {
+    fn new(gui: &mut Gui) -> Self {
+        let texture = gui.texture(&TEXTURES.id("pusher"));
+
+        let mut model = |name: &str| {
+            let mesh = crate::gui::asset::load_mesh(name);
+            Rc::new(gui.make_primitive(vec![mesh.bind(texture.clone())]))
+        };
+
+        Self {
+            model_compressed: model("pusher_compressed"),
+            model_extended: model("pusher_extended"),
+        }
+    }
+}
+
+pub struct PusherView {
+    pusher: Rc<Primitive>,
+
+    /// Rotation of the model, which faces east (towards positive X) by default.
+    rotation: crate::gui::Quat,
+
+    contents: Box<View>,
+}
+
+impl ViewInstance for PusherView {}
+impl Drawable for PusherView {
+    fn draw(&mut self, dcf: &mut crate::gui::Dcf) {
+        self.pusher
+            .draw(&mut dcf.tfed(crate::gui::Affine3::from_quat(self.rotation)));
+        self.contents
+            .draw(&mut dcf.scaled(crate::gui::Vec3::splat(0.5)));
+    }
+}
+
+impl Presentation for Pusher {
+    type Kind = PusherKind;
+    type View = PusherView;
+
+    fn view(&self, kind: &Self::Kind, rsrc: &Resources) -> Self::View {
+        let rotation = crate::gui::Quat::from_rotation_arc(
+            crate::gui::Vec3::X,
+            self.facing.offset().as_vec3(),
+        );
+
+        match &self.state {
+            PusherState::Holds(contents) => Self::View {
+                pusher: kind.model_compressed.clone(),
+                rotation,
+                contents: Box::new(view(contents, rsrc)),
+            },
+            PusherState::Extended => Self::View {
+                pusher: kind.model_extended.clone(),
+                rotation,
+                contents: Box::new(View::Air(AirView)),
+            },
+        }
+    }
+}
@@ -0,0 +1,41 @@
+//! Presentation of blocks that shape runs.
+
+use crate::client::block::*;
+
+impl Presentation for Start {
+    type Kind = PlainKind;
+    type View = Cuboid;
+    fn view(&self, kind: &Self::Kind, _: &Resources) -> Self::View {
+        let color = OpaqueColor::rgb(Vec3::new(0.2, 0.8, 0.3));
+        Cuboid::new(&kind.model, Vec3::splat(-0.5), Vec3::splat(0.5), color)
+    }
+}
+
+impl Presentation for Finish {
+    type Kind = PlainKind;
+    type View = Cuboid;
+    fn view(&self, kind: &Self::Kind, _: &Resources) -> Self::View {
+        // A thin line on the floor of the cell, since the gate itself is not solid
+        let color = OpaqueColor::rgb(Vec3::new(0.95, 0.95, 0.95));
+        let max = Vec3::new(0.5, 0.5, -0.5 + 1.0 / 32.0);
+        Cuboid::new(&kind.model, Vec3::splat(-0.5), max, color)
+    }
+}
+
+impl Presentation for Checkpoint {
+    type Kind = PlainKind;
+    type View = Cuboid;
+    fn view(&self, kind: &Self::Kind, _: &Resources) -> Self::View {
+        let color = OpaqueColor::rgb(Vec3::new(0.2, 0.5, 0.9));
+        Cuboid::new(&kind.model, Vec3::splat(-0.5), Vec3::splat(0.5), color)
+    }
+}
+
+impl Presentation for Hazard {
+    type Kind = PlainKind;
+    type View = Cuboid;
+    fn view(&self, kind: &Self::Kind, _: &Resources) -> Self::View {
+        let color = OpaqueColor::rgb(Vec3::new(1.0, 0.45, 0.0));
+        Cuboid::new(&kind.model, Vec3::splat(-0.5), Vec3::splat(0.5), color)
+    }
+}
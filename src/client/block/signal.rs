@@ -0,0 +1,89 @@
+//! Presentation of blocks that emit, conduct and consume signals.
+//!
+//! These blocks have no dedicated textures yet; they are drawn as tinted [`Cuboid`]s instead.
+
+use crate::{client::block::*, content::block::MAX_RELAY_DELAY, world::signal::MAX_POWER};
+
+/// Tint of sources that emit power.
+const ACTIVE: OpaqueColor = OpaqueColor::rgb(Vec3::new(1.0, 0.2, 0.1));
+
+/// Tint of sources that emit no power.
+const INACTIVE: OpaqueColor = OpaqueColor::rgb(Vec3::new(0.4, 0.1, 0.1));
+
+/// Get the tint of a source that is _active_.
+fn source_color(active: bool) -> OpaqueColor {
+    if active { ACTIVE } else { INACTIVE }
+}
+
+impl Presentation for Lever {
+    type Kind = PlainKind;
+    type View = Cuboid;
+    fn view(&self, kind: &Self::Kind, _: &Resources) -> Self::View {
+        let min = Vec3::new(-0.1, -0.1, -0.5);
+        let max = Vec3::new(0.1, 0.1, 0.1);
+        Cuboid::new(&kind.model, min, max, source_color(self.on))
+    }
+}
+
+impl Presentation for Button {
+    type Kind = PlainKind;
+    type View = Cuboid;
+    fn view(&self, kind: &Self::Kind, _: &Resources) -> Self::View {
+        let height = if self.pressed { 0.05 } else { 0.125 };
+        let min = Vec3::new(-0.2, -0.2, -0.5);
+        let max = Vec3::new(0.2, 0.2, -0.5 + height);
+        Cuboid::new(&kind.model, min, max, source_color(self.pressed))
+    }
+}
+
+impl Presentation for Plate {
+    type Kind = PlainKind;
+    type View = Cuboid;
+    fn view(&self, kind: &Self::Kind, _: &Resources) -> Self::View {
+        let height = if self.pressed { 1.0 / 32.0 } else { 1.0 / 16.0 };
+        let min = Vec3::new(-0.45, -0.45, -0.5);
+        let max = Vec3::new(0.45, 0.45, -0.5 + height);
+        Cuboid::new(&kind.model, min, max, source_color(self.pressed))
+    }
+}
+
+impl Presentation for Wire {
+    type Kind = PlainKind;
+    type View = Cuboid;
+    fn view(&self, kind: &Self::Kind, _: &Resources) -> Self::View {
+        // Brighter for stronger signals
+        let strength = self.power as Float / MAX_POWER as Float;
+        let color = OpaqueColor::rgb(Vec3::new(0.3 + 0.7 * strength, 0.05, 0.05));
+        Cuboid::new(&kind.model, Vec3::splat(-0.125), Vec3::splat(0.125), color)
+    }
+}
+
+impl Presentation for Relay {
+    type Kind = PlainKind;
+    type View = Cuboid;
+    fn view(&self, kind: &Self::Kind, _: &Resources) -> Self::View {
+        // A bar along the facing axis that is longer for longer delays
+        let length = 0.25 + 0.75 * self.delay as Float / MAX_RELAY_DELAY as Float;
+        let mut min = Vec3::splat(-0.125);
+        let mut max = Vec3::splat(0.125);
+        min[self.facing.axis()] = -length / 2.0;
+        max[self.facing.axis()] = length / 2.0;
+        Cuboid::new(&kind.model, min, max, source_color(self.powered))
+    }
+}
+
+impl Presentation for Door {
+    type Kind = PlainKind;
+    type View = Cuboid;
+    fn view(&self, kind: &Self::Kind, _: &Resources) -> Self::View {
+        let color = OpaqueColor::rgb(Vec3::new(0.55, 0.35, 0.2));
+
+        // An open door is folded against the west face of its cell
+        let max = if self.open {
+            Vec3::new(-0.375, 0.5, 0.5)
+        } else {
+            Vec3::splat(0.5)
+        };
+        Cuboid::new(&kind.model, Vec3::splat(-0.5), max, color)
+    }
+}
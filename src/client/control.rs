@@ -1,6 +1,6 @@
 //! Intepretation of GUI inputs as simulation controls.
 
-use std::collections::VecDeque;
+use std::{collections::VecDeque, sync::Arc};
 
 use winit::{
     event::{ElementState, KeyEvent},
@@ -9,7 +9,7 @@ use winit::{
 
 use crate::{
     client::view::Parameters,
-    world::{Event, Vec3},
+    world::{Event, Vec2, Vec3, savestate::Savestate},
 };
 
 /// Noclip (unaffected by collisions) camera state, otherwise known as a free camera.
@@ -60,6 +60,27 @@ impl Control {
         other.chain(set_camera_rotation)
     }
 
+    /// Request that the player character respawns at its last checkpoint, unless a request is
+    /// already pending.
+    pub fn request_respawn(&mut self) {
+        let pending = self
+            .pending
+            .iter()
+            .any(|event| matches!(event, Event::RespawnPlayerCharacter));
+        if !pending {
+            self.pending.push_back(Event::RespawnPlayerCharacter);
+        }
+    }
+
+    /// Adopt the rotation of the player character in _world_ after it was changed by the
+    /// simulation, e.g. when the character respawned. Has no effect on the noclip camera.
+    pub fn sync_rotation(&mut self, world: &crate::world::World) {
+        if self.noclip.is_none() {
+            self.last_camera_rotation = world.player.rotation;
+            self.pending_set_camera_rotation = None;
+        }
+    }
+
     /// Adjust view parameters according to inputs.
     pub fn tweak_view_parameters(&mut self, params: &mut Parameters) {
         use super::view::Camera::*;
@@ -72,6 +93,13 @@ impl Control {
         }
     }
 
+    /// Compute the player character movement controls according to keyboard inputs.
+    fn character_move_direction(&self) -> Vec2 {
+        self.keyboard_camera_move_state
+            .truncate()
+            .clamp_length_max(1.0)
+    }
+
     /// Enable or disable the noclip camera.
     ///
     /// _world_ is used to initialize noclip camera equal to character camera.s
@@ -79,7 +107,7 @@ impl Control {
         if let Some(_) = self.noclip.take() {
             // Disable noclip
             self.pending.push_back(Event::MovePlayerCharacter {
-                direction: self.keyboard_camera_move_state.clamp_length_max(1.0),
+                direction: self.character_move_direction(),
             });
             self.last_camera_rotation = world.player.rotation;
         } else {
@@ -89,14 +117,14 @@ impl Control {
                 velocity: world.player.velocity,
             });
             self.pending.push_back(Event::MovePlayerCharacter {
-                direction: Vec3::ZERO,
+                direction: Vec2::ZERO,
             });
         }
     }
 
     /// Render control-specific UI elements and update controls state.
     pub fn draw(&mut self, dcf: &mut crate::gui::Dcf) {
-        use crate::gui::{Float, Mat3};
+        use crate::world::{Float, math};
 
         if let Some(noclip) = &mut self.noclip {
             let dt: Float = dcf.delta_time().as_secs_f32();
@@ -104,16 +132,20 @@ impl Control {
             const CONTROL_ACCELERATION: Float = 50.0;
             const CONTROL_SPEED: Float = 5.0;
 
-            let target = Mat3::from_rotation_z(-self.last_camera_rotation.yaw)
-                * self.keyboard_camera_move_state
-                * CONTROL_SPEED;
+            /// Base 2 logarithm of the fraction of velocity retained after one second.
+            const DRAG_LOG2: Float = -2.0;
+
+            let target = math::rotate_z(
+                self.keyboard_camera_move_state,
+                -self.last_camera_rotation.yaw,
+            ) * CONTROL_SPEED;
 
             let dv = target - noclip.velocity;
             let dv = dv.clamp_length_max(CONTROL_ACCELERATION * dt);
             noclip.velocity += dv;
 
             noclip.position += noclip.velocity * dt;
-            noclip.velocity *= (0.25 as Float).powf(dt); // TODO powf is not deterministic
+            noclip.velocity *= math::exp2(DRAG_LOG2 * dt);
         }
     }
 
@@ -151,13 +183,83 @@ impl Control {
                         dmove *= -1.0;
                     };
                     self.keyboard_camera_move_state += dmove;
-                    if self.noclip.is_none() {
+                    if self.noclip.is_none() && dmove.z == 0.0 {
                         self.pending.push_back(Event::MovePlayerCharacter {
-                            direction: self.keyboard_camera_move_state.clamp_length_max(1.0),
+                            direction: self.character_move_direction(),
                         });
                     }
                 }
 
+                if let KeyEvent {
+                    physical_key: PhysicalKey::Code(KeyCode::Space),
+                    state: ElementState::Pressed,
+                    repeat: false,
+                    ..
+                } = key_event
+                    && self.noclip.is_none()
+                {
+                    self.pending.push_back(Event::JumpPlayerCharacter);
+                }
+
+                if let KeyEvent {
+                    physical_key: PhysicalKey::Code(KeyCode::KeyE),
+                    state: ElementState::Pressed,
+                    repeat: false,
+                    ..
+                } = key_event
+                    && self.noclip.is_none()
+                {
+                    self.pending.push_back(Event::UseBlock {
+                        direction: self.last_camera_rotation.direction(),
+                    });
+                }
+
+                if let KeyEvent {
+                    physical_key: PhysicalKey::Code(KeyCode::Backspace),
+                    state: ElementState::Pressed,
+                    repeat: false,
+                    ..
+                } = key_event
+                    && self.noclip.is_none()
+                {
+                    self.request_respawn();
+                }
+
+                if let KeyEvent {
+                    physical_key: PhysicalKey::Code(KeyCode::Delete),
+                    state: ElementState::Pressed,
+                    repeat: false,
+                    ..
+                } = key_event
+                    && self.noclip.is_none()
+                {
+                    self.pending.push_back(Event::Restart);
+                }
+
+                if let KeyEvent {
+                    physical_key: PhysicalKey::Code(KeyCode::F5),
+                    state: ElementState::Pressed,
+                    repeat: false,
+                    ..
+                } = key_event
+                {
+                    crate::client::save_savestate(&Savestate::capture(world));
+                }
+
+                if let KeyEvent {
+                    physical_key: PhysicalKey::Code(KeyCode::F9),
+                    state: ElementState::Pressed,
+                    repeat: false,
+                    ..
+                } = key_event
+                    && self.noclip.is_none()
+                    && let Some(savestate) = crate::client::load_savestate()
+                {
+                    self.pending.push_back(Event::LoadSavestate {
+                        savestate: Arc::new(savestate),
+                    });
+                }
+
                 if let KeyEvent {
                     physical_key: PhysicalKey::Code(KeyCode::Escape),
                     state: ElementState::Pressed,
@@ -1,7 +1,7 @@
 //! Graphical presentation of [`World`].
 
 use crate::{
-    content::Resources,
+    client::{Resources, block},
     gui::{Affine3, Drawable, Float, Mat4, OpaqueColor, Vec3},
     world::{Level, World, character::Character},
 };
@@ -99,12 +99,12 @@ fn remap_depth(new_min: Float, new_max: crate::gui::Float) -> Mat4 {
     ])
 }
 
-fn draw_level(dcf: &mut crate::gui::Dcf, level: &Level, rsrc: &Resources) {
-    let mut dcf = dcf.shifted(level.position);
-    let mut dcf = dcf.tfed(Affine3::from_rotation_z(-level.yaw));
+fn draw_level(dcf: &mut crate::gui::Dcf, level: &Level, rsrc: &Resources, tick_progress: Float) {
+    let mut dcf = dcf.tfed(level.transform());
 
     for (pos, block) in level.blocks.pos_iter() {
-        block.view(&rsrc).draw(&mut dcf.shifted(pos.as_vec3()));
+        let offset = block::offset(block, tick_progress);
+        block::view(block, rsrc).draw(&mut dcf.shifted(pos.as_vec3() + offset));
     }
 }
 
@@ -117,12 +117,15 @@ fn draw_character(dcf: &mut crate::gui::Dcf, character: &Character) {
 }
 
 impl View {
+    /// Draw _world_ as it is _tick_progress_ of the way from the last logic tick to the next one,
+    /// between `0.0` and `1.0`.
     pub fn draw(
         &mut self,
         dcf: &mut crate::gui::Dcf,
         world: &World,
         rsrc: &Resources,
         params: &Parameters,
+        tick_progress: Float,
     ) {
         // Draw 3D scene
 
@@ -160,7 +163,7 @@ impl View {
             .draw(&mut dcf.shifted(Vec3::Z * -3.0).scaled(Vec3::splat(10.0)));
 
         for level in &world.levels {
-            draw_level(dcf, level, rsrc);
+            draw_level(dcf, level, rsrc, tick_progress);
         }
         draw_character(dcf, &world.player);
 
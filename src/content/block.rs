@@ -1,212 +1,351 @@
 //! Block kinds and abstractions.
 //!
-//! There are three main types associated with blocks: [block instances](Instance), block
-//! [kinds](KindInstance) and block [views](ViewInstance):
-//! - Block kinds: one per type of block, such as "stone" or "sand"; also owns GUI resources.
-//!   - [`KindInstance`]\: trait implemented by every block kind
-//!   - [`Kinds`]\: a registry with one of every known kind
-//!   - [`KindRef`]\: a reference to a kind; essentially just `&dyn KindInstance`
-//! - Block views: look-and-feel model of a specific block with its animation state, etc.
-//!   - [`ViewInstance`]\: trait implemented by every block view
-//!   - [`View`]\: an enum of every possible `ViewInstance` for dispatch.
+//! This module only describes blocks as part of game state and can be used headless. Their
+//! presentation, including GUI resources, lives in [`crate::client::block`].
+//!
+//! - Block kinds: one per type of block, such as "stone" or "sand".
+//!   - [`KindId`]\: a stable numeric identifier of a kind
+//!   - [`for_all_blocks!`]\: the registry of all known kinds
 //! - Block instances: the state of a specific block in the level.
 //!   - [`Instance`]\: trait implemented by every block state type.
 //!   - [`Block`]\: an enum of every possible `Instance` for dispatch.
+//!   - [`Serialized`]\: compact representation of block state for storage.
+//!   - [`state`]\: human-readable representation of block state with named properties.
+//!   - [`Physics`]\: physical properties of a block state, such as its collision shape.
 
 mod basic;
+pub mod physics;
 mod pusher;
+mod run;
+mod sand;
+mod signal;
+pub mod state;
 
-use std::rc::Rc;
-
-use crate::{
-    content::Resources,
-    gui::{Drawable, Gui, Primitive, Texture},
-};
-use basic::*;
-use pusher::*;
+use crate::world::{Direction, signal::Power, update};
+pub use basic::*;
+pub use physics::Physics;
+pub use pusher::*;
+pub use run::*;
+pub use sand::*;
+pub use signal::*;
+use state::{Property, StateError, Value};
 
 /// Serialized representation of a single block. Kind identifier is not included.
-pub struct Serialized(pub u32);
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+pub struct Serialized {
+    /// Kind-specific state of the block.
+    pub state: u32,
 
-/// A single type of block, such as "stone" or "sand".
-///
-/// Responsible for initialization and ownership of resources used by blocks of this kind, such as
-/// textures or models.
-pub trait KindInstance {
-    /// Initialize assets used by this kind.
-    ///
-    /// This is expected to be called once when application starts.
-    fn new(gui: &mut Gui) -> Self;
+    /// Blocks contained in this block, such as the contents of a [`Pusher`], in kind-specific
+    /// order.
+    pub nested: Vec<Nested>,
+}
+
+impl Serialized {
+    /// Create a `Serialized` with given state and no nested blocks.
+    pub fn new(state: u32) -> Self {
+        Self {
+            state,
+            nested: Vec::new(),
+        }
+    }
+
+    /// Check that this is the default `Serialized`, which is the only valid state of blocks of
+    /// _kind_ that have no properties.
+    pub fn check_default(&self, kind: &'static str) -> Result<(), StateError> {
+        if *self == Self::default() {
+            Ok(())
+        } else {
+            Err(StateError::InvalidState {
+                kind,
+                reason: format!("expected no state, got {self:?}"),
+            })
+        }
+    }
+
+    /// Check that this is a `Serialized` with state `0` or `1` and no nested blocks, which are the
+    /// only valid states of blocks of _kind_ that have a single flag, and get the flag.
+    pub fn check_flag(&self, kind: &'static str) -> Result<bool, StateError> {
+        if self.state <= 1 && self.nested.is_empty() {
+            Ok(self.state == 1)
+        } else {
+            Err(StateError::InvalidState {
+                kind,
+                reason: format!("expected state 0 or 1, got {self:?}"),
+            })
+        }
+    }
 }
 
-/// The GUI representation of a specific state of a single block instance.
+/// Maximum number of blocks a block may be nested in.
 ///
-/// For now, this only includes its graphics; audio and other kinds of GUI feedback will be included
-/// in the future.
+/// Enforced when loading and parsing blocks to avoid stack overflows caused by corrupted input.
+pub const MAX_NESTING_DEPTH: usize = 64;
+
+/// Serialized representation of a block contained in another block, including its kind.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Nested {
+    /// Name of the kind of the block, as accepted by [`KindId::from_name`].
+    pub kind: String,
+
+    /// The state of the block.
+    pub data: Serialized,
+}
+
+/// A stable numeric identifier of a block kind.
 ///
-/// A new view is generated each time the block state updates, so the state of the block can be
-/// baked into the ViewInstance, especially since it doesn't have access to the block state from the
-/// world when it is rendered. This is to facilitate their use outside of world rendering contexts,
-/// such as in UI elements.
-pub trait ViewInstance: Drawable {}
+/// IDs are assigned in the order kinds are listed in [`for_all_blocks!`], starting from zero. To keep
+/// existing IDs stable, new kinds must be appended to the end of the list, and kinds must never be
+/// reordered or removed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct KindId(u16);
+
+impl KindId {
+    /// The number of known block kinds. All IDs are less than this value.
+    pub const COUNT: usize = KIND_NAMES.len();
+
+    /// Get the ID with numeric value _id_, if a kind with this ID exists.
+    pub fn new(id: u16) -> Option<Self> {
+        ((id as usize) < Self::COUNT).then_some(Self(id))
+    }
+
+    /// Find the ID of the kind called _name_.
+    pub fn from_name(name: &str) -> Option<Self> {
+        KIND_NAMES
+            .iter()
+            .position(|n| *n == name)
+            .map(|index| Self(index as u16))
+    }
+
+    /// Iterate over the IDs of all known block kinds in ascending order.
+    pub fn all() -> impl Iterator<Item = Self> {
+        (0..Self::COUNT as u16).map(Self)
+    }
+
+    /// Get the numeric value of this ID.
+    pub fn get(self) -> u16 {
+        self.0
+    }
+
+    /// Get the name of the kind with this ID.
+    pub fn name(self) -> &'static str {
+        KIND_NAMES[self.0 as usize]
+    }
+}
+
+impl std::fmt::Display for KindId {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.name())
+    }
+}
 
 /// The state of a single instance of a block in a world.
 ///
 /// This should be empty unless the block contains some modifiable properties.
-pub trait Instance {
-    /// The kind of this block.
-    type Kind: KindInstance;
+///
+/// See [`client::block::Presentation`](crate::client::block::Presentation) for the GUI
+/// representation of instances.
+pub trait Instance: Sized + Clone {
+    /// Deserialize `Self`, rejecting data that does not describe a valid state.
+    fn from(data: &Serialized) -> Result<Self, StateError>;
 
-    /// The type used for the view of this block.
-    type View: ViewInstance;
+    /// Serialize `self`. This is the inverse of [`Self::from`].
+    fn serialize(&self) -> Serialized;
 
-    /// Obtain a view for this block state.
-    ///
-    /// This method should execute quickly to avoid lag. Cache all expensive computation in `Kind`;
-    /// for many block kinds, the entire view can be pre-initialized and shared via [`Rc`].
-    fn view(&self, kind: &Self::Kind, rsrc: &Resources) -> Self::View;
+    /// Named properties of blocks of this kind. See [`state`].
+    const PROPERTIES: &'static [Property] = &[];
 
-    /// Deserialize `Self`.
-    fn from(data: &Serialized) -> Self;
-}
+    /// Get the values of [`Self::PROPERTIES`] for `self`, in the same order.
+    fn properties(&self) -> Vec<Value> {
+        Vec::new()
+    }
 
-////////////////////////////////////////////////////////////////////////////////////////////////////
+    /// Create an instance from the values of [`Self::PROPERTIES`], in the same order.
+    ///
+    /// The values are guaranteed to match property types. Contradictory combinations of values
+    /// should be rejected.
+    fn from_properties(values: Vec<Value>) -> Result<Self, StateError> {
+        debug_assert!(values.is_empty());
+        Self::from(&Serialized::default())
+    }
 
-/// Texture group of bundled block textures.
-const TEXTURES: crate::gui::TextureGroup = crate::gui::TextureGroup {};
+    /// Get the physical properties of this block state.
+    ///
+    /// Most blocks are [ordinary solid cubes](Physics::SOLID).
+    fn physics(&self) -> Physics {
+        Physics::SOLID
+    }
 
-/// A block view that renders an opaque cube.
-///
-/// This view is static, and so it should be pre-initialized in [`KindInstance`].
-#[derive(Clone)]
-pub struct FullCube(Rc<Primitive>);
-
-impl FullCube {
-    /// Create a `FullCube` view with a given texture.
-    fn new(texture: &Rc<Texture>, gui: &mut Gui) -> Self {
-        let mwt = crate::gui::Mesh::tmp_ppp(
-            crate::gui::Vec3::splat(-0.5),
-            crate::gui::Vec3::X,
-            crate::gui::Vec3::Y,
-            crate::gui::Vec3::Z,
-            &texture,
-        );
-
-        Self(Rc::new(gui.make_primitive(mwt)))
+    /// Check whether this block can be moved to another cell by mechanisms such as a [`Pusher`].
+    ///
+    /// Most blocks can be moved.
+    fn is_movable(&self) -> bool {
+        true
     }
-}
 
-impl ViewInstance for FullCube {}
-impl Drawable for FullCube {
-    fn draw(&mut self, dcf: &mut crate::gui::Dcf) {
-        self.0.draw(dcf);
+    /// Get the [signal power](crate::world::signal) this block emits towards its neighbour in
+    /// _direction_.
+    ///
+    /// Most blocks emit nothing.
+    fn signal_output(&self, _direction: Direction) -> Power {
+        0
     }
+
+    /// React to an [update](crate::world::update) of this block, described by _ctx_.
+    ///
+    /// `self` is a copy of the block taken before the update; changes must be applied through
+    /// _ctx_. Most blocks do not react to anything.
+    fn update(&self, _ctx: &mut update::Context) {}
 }
 
 ////////////////////////////////////////////////////////////////////////////////////////////////////
 
-/// Creates a registry of all known block kinds and generates boilerplate types and methods.
-///
-/// ## Usage
-/// ```
-/// // At module level
-///
-/// all_blocks! {
-///     // snake_case_id: BlockInstanceType
-///     stone: Stone,
-///     sand: Sand,
-/// }
-/// ```
+/// Generates [`Block`] and its boilerplate methods for the block kinds listed by
+/// [`for_all_blocks!`].
 macro_rules! all_blocks {
     { $($snake_case:ident: $title_case:ident),+ $(,)? } => {
-        /// A reference to some [`KindInstance`] value from the [registry](Kinds).
-        pub enum KindRef<'a> {
+        /// Names of all known block kinds, indexed by [`KindId`].
+        const KIND_NAMES: &[&str] = &[$(stringify!($snake_case)),*];
+
+        /// A single block instance; an [`Instance`] value.
+        #[derive(Clone)]
+        pub enum Block {
             $(
-                $title_case(&'a <$title_case as Instance>::Kind),
+                $title_case($title_case),
             )*
         }
 
-        impl<'a> KindRef<'a> {
-            /// Create a block instance with given state.
-            pub fn instantiate(&self, data: &Serialized) -> Block {
-                match self {
+        impl Block {
+            /// Create a block instance of the kind named _kind_ with given state.
+            pub fn deserialize(kind: &str, data: &Serialized) -> Result<Block, StateError> {
+                match kind {
                     $(
-                        KindRef::$title_case(_) => {
-                            Block::$title_case(<$title_case as Instance>::from(data))
+                        stringify!($snake_case) => {
+                            Ok(Block::$title_case(<$title_case as Instance>::from(data)?))
                         }
                     )*
+                    _ => Err(StateError::UnknownKind { kind: kind.into() }),
                 }
             }
-        }
 
-        /// A GUI representation of a specific state of a specific block; a [`ViewInstance`] value.
-        pub enum View {
-            $(
-                $title_case(<$title_case as Instance>::View),
-            )*
-        }
+            /// Find the name of the kind called _kind_ as a `'static` string.
+            pub fn canonical_name(kind: &str) -> Option<&'static str> {
+                KindId::from_name(kind).map(KindId::name)
+            }
+
+            /// Get the [properties](state) declared by the kind called _kind_.
+            pub fn properties_of(kind: &str) -> Result<&'static [Property], StateError> {
+                match kind {
+                    $(
+                        stringify!($snake_case) => Ok(<$title_case as Instance>::PROPERTIES),
+                    )*
+                    _ => Err(StateError::UnknownKind { kind: kind.into() }),
+                }
+            }
+
+            /// Create a block instance of the kind called _kind_ from the values of its
+            /// [properties](Self::properties_of), in the same order.
+            ///
+            /// # Panics
+            /// Panics if values do not match the properties.
+            pub fn from_properties(kind: &str, values: Vec<Value>) -> Result<Block, StateError> {
+                match kind {
+                    $(
+                        stringify!($snake_case) => Ok(Block::$title_case(
+                            <$title_case as Instance>::from_properties(values)?,
+                        )),
+                    )*
+                    _ => Err(StateError::UnknownKind { kind: kind.into() }),
+                }
+            }
 
-        impl Drawable for View {
-            fn draw(&mut self, dcf: &mut crate::gui::Dcf) {
+            /// Get the [properties](state) declared by the kind of this block.
+            pub fn kind_properties(&self) -> &'static [Property] {
                 match self {
                     $(
-                        View::$title_case(instance) => instance.draw(dcf),
+                        Block::$title_case(_) => <$title_case as Instance>::PROPERTIES,
                     )*
                 }
             }
-        }
 
-        /// A single block instance; an [`Instance`] value.
-        pub enum Block {
-            $(
-                $title_case($title_case),
-            )*
-        }
+            /// Get the values of the [properties](Self::kind_properties) of this block, in the same
+            /// order.
+            pub fn properties(&self) -> Vec<Value> {
+                match self {
+                    $(
+                        Block::$title_case(instance) => instance.properties(),
+                    )*
+                }
+            }
 
-        impl Block {
-            /// Obtain a view for this block state.
-            ///
-            /// The view will have the state of this block baked into it.
-            pub fn view(&self, rsrc: &Resources) -> View {
+            /// Get the [physical properties](Physics) of this block.
+            pub fn physics(&self) -> Physics {
                 match self {
                     $(
-                        Block::$title_case(instance) => {
-                            View::$title_case(instance.view(&rsrc.blocks.$snake_case, rsrc))
-                        }
+                        Block::$title_case(instance) => instance.physics(),
                     )*
                 }
             }
-        }
 
-        /// All resources required by blocks, such as textures and models, as well as the registry
-        /// of all known block kinds.
-        ///
-        /// This struct should be initialized once when game first loads, as [`Self::new`] is rather
-        /// expensive.
-        pub struct Kinds {
-            $(
-                $snake_case: <$title_case as Instance>::Kind,
-            )*
-        }
+            /// Check whether this block can be moved to another cell by mechanisms such as a
+            /// [`Pusher`].
+            pub fn is_movable(&self) -> bool {
+                match self {
+                    $(
+                        Block::$title_case(instance) => instance.is_movable(),
+                    )*
+                }
+            }
+
+            /// Get the [signal power](crate::world::signal) this block emits towards its neighbour
+            /// in _direction_.
+            pub fn signal_output(&self, direction: Direction) -> Power {
+                match self {
+                    $(
+                        Block::$title_case(instance) => instance.signal_output(direction),
+                    )*
+                }
+            }
+
+            /// React to an [update](crate::world::update) of this block, described by _ctx_.
+            pub fn update(&self, ctx: &mut update::Context) {
+                match self {
+                    $(
+                        Block::$title_case(instance) => instance.update(ctx),
+                    )*
+                }
+            }
 
-        impl Kinds {
-            /// Initializes all runtime resources content needs: loads textures, generates models,
-            /// etc.
-            pub fn new(gui: &mut crate::gui::Gui) -> Self {
-                Self {
+            /// Get the name of the kind of this block, as accepted by [`KindId::from_name`].
+            pub fn kind_name(&self) -> &'static str {
+                match self {
                     $(
-                        $snake_case: <$title_case as Instance>::Kind::new(gui),
+                        Block::$title_case(_) => stringify!($snake_case),
                     )*
                 }
             }
 
-            /// Find a block kind by its name.
-            pub fn get<'a> (&'a self, name: &str) -> Option<KindRef<'a>> {
-                match name {
+            /// Get the stable ID of the kind of this block.
+            pub fn kind_id(&self) -> KindId {
+                KindId::from_name(self.kind_name()).unwrap()
+            }
+
+            /// Serialize the state of this block. Kind identifier is not included, see
+            /// [`Self::kind_name`].
+            pub fn serialize(&self) -> Serialized {
+                match self {
                     $(
-                        stringify!($snake_case) => Some(KindRef::$title_case(&self.$snake_case)),
+                        Block::$title_case(instance) => instance.serialize(),
                     )*
-                    _ => None,
+                }
+            }
+
+            /// Serialize this block including its kind, so that it can be nested in another
+            /// block's [`Serialized`].
+            pub fn serialize_nested(&self) -> Nested {
+                Nested {
+                    kind: self.kind_name().into(),
+                    data: self.serialize(),
                 }
             }
         }
@@ -219,9 +358,41 @@ impl Default for Block {
     }
 }
 
-all_blocks! {
-    air: Air,
-    pusher: Pusher,
-    sand: Sand,
-    stone: Stone,
+/// The registry of all known block kinds: invokes macro _callback_ with the list of all kinds.
+///
+/// Each kind is listed as `snake_case_id: InstanceType`. The position of a kind in the list
+/// determines its [`KindId`], so new kinds must be appended to the end.
+///
+/// ## Usage
+/// ```ignore
+/// macro_rules! my_macro {
+///     { $($snake_case:ident: $title_case:ident),+ $(,)? } => { /* ... */ };
+/// }
+///
+/// // At module level, with all instance types in scope
+/// for_all_blocks!(my_macro);
+/// ```
+macro_rules! for_all_blocks {
+    ($callback:ident) => {
+        $callback! {
+            air: Air,
+            pusher: Pusher,
+            sand: Sand,
+            stone: Stone,
+            button: Button,
+            door: Door,
+            lever: Lever,
+            plate: Plate,
+            relay: Relay,
+            wire: Wire,
+            finish: Finish,
+            start: Start,
+            checkpoint: Checkpoint,
+            hazard: Hazard,
+        }
+    };
 }
+#[cfg(feature = "gui")]
+pub(crate) use for_all_blocks;
+
+for_all_blocks!(all_blocks);
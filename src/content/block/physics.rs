@@ -0,0 +1,56 @@
+//! Physical properties of blocks.
+//!
+//! Every block state declares its [`Physics`] via
+//! [`Instance::physics`](crate::content::block::Instance::physics). Physical properties are part of
+//! game logic, so they are available from [`Block::physics`](crate::content::block::Block::physics)
+//! without any GUI resources.
+
+use crate::world::{Float, Vec3, collision::Aabb};
+
+/// Physical properties of a specific block state.
+#[derive(Debug, Clone, Copy)]
+pub struct Physics {
+    /// Boxes that make up the collision shape of the block, relative to its center. Empty for
+    /// blocks that do not obstruct movement.
+    ///
+    /// All boxes must lie within the block, i.e. between `-0.5` and `0.5` along each axis.
+    pub shape: &'static [Aabb],
+
+    /// How much grip the surfaces of the block provide, with `1.0` being normal and `0.0` being
+    /// perfectly slippery.
+    pub friction: Float,
+
+    /// Fraction of speed that bodies retain when bouncing off the block, with `0.0` meaning no
+    /// bounce at all.
+    pub bounciness: Float,
+
+    /// Whether characters can climb the block.
+    pub climbable: bool,
+}
+
+impl Physics {
+    /// The collision shape of a block that fills its entire space.
+    pub const FULL_CUBE: &'static [Aabb] = &[Aabb {
+        min: Vec3::splat(-0.5),
+        max: Vec3::splat(0.5),
+    }];
+
+    /// Properties of an ordinary solid block that fills its entire space.
+    pub const SOLID: Self = Self {
+        shape: Self::FULL_CUBE,
+        friction: 1.0,
+        bounciness: 0.0,
+        climbable: false,
+    };
+
+    /// Properties of a block that does not interact with anything physically.
+    pub const EMPTY: Self = Self {
+        shape: &[],
+        ..Self::SOLID
+    };
+
+    /// Check whether the block obstructs movement at all.
+    pub fn is_solid(&self) -> bool {
+        !self.shape.is_empty()
+    }
+}
@@ -1,67 +1,392 @@
-use crate::content::block::*;
+use std::sync::LazyLock;
 
-pub struct PusherKind {
-    model_compressed: Rc<Primitive>,
-    model_extended: Rc<Primitive>,
+use crate::{
+    content::block::{state::PropertyType, *},
+    world::{
+        Direction, Float, Vec3,
+        collision::Aabb,
+        signal,
+        update::{self, Cause},
+    },
+};
+
+/// The largest allowed [push limit](Pusher::limit).
+pub const MAX_PUSH_LIMIT: u32 = 16;
+
+/// Number of logic ticks an extended pusher stays extended before it retracts.
+pub const RETRACT_DELAY: u64 = 10;
+
+/// Whether a [`Pusher`] is compressed or extended.
+#[derive(Clone)]
+pub enum PusherState {
+    /// The pusher is compressed and holds a block, possibly air.
+    Holds(Box<Block>),
+
+    /// The pusher is extended. It cannot hold anything.
+    Extended,
 }
 
-impl KindInstance for PusherKind {
-    fn new(gui: &mut Gui) -> Self {
-        let texture = gui.texture(&TEXTURES.id("pusher"));
+/// Collision shapes of extended pushers indexed by the axis of their facing: a frame of beams
+/// along the edges and thin plates on the two faces perpendicular to the axis.
+static EXTENDED_SHAPES: LazyLock<[Vec<Aabb>; 3]> = LazyLock::new(|| {
+    const BEAM: Float = 0.25;
+    const PLATE: Float = 1.0 / 16.0;
 
-        let mut model = |name: &str| {
-            let mesh = crate::gui::asset::load_mesh(name);
-            Rc::new(gui.make_primitive(vec![mesh.bind(texture.clone())]))
-        };
+    let make_box = |ranges: [(Float, Float); 3]| Aabb {
+        min: Vec3::from_array(ranges.map(|range| range.0)),
+        max: Vec3::from_array(ranges.map(|range| range.1)),
+    };
+    let edges = [(-0.5, -0.5 + BEAM), (0.5 - BEAM, 0.5)];
+    let plates = [(-0.5, -0.5 + PLATE), (0.5 - PLATE, 0.5)];
+
+    std::array::from_fn(|facing_axis| {
+        let mut shape = Vec::new();
 
-        Self {
-            model_compressed: model("pusher_compressed"),
-            model_extended: model("pusher_extended"),
+        for along in 0..3 {
+            for a in edges {
+                for b in edges {
+                    let mut ranges = [(-0.5, 0.5); 3];
+                    ranges[(along + 1) % 3] = a;
+                    ranges[(along + 2) % 3] = b;
+                    shape.push(make_box(ranges));
+                }
+            }
         }
-    }
-}
 
-pub struct PusherView {
-    pusher: Rc<Primitive>,
-    contents: Box<View>,
-}
+        for plate in plates {
+            let mut ranges = [(-0.5, 0.5); 3];
+            ranges[facing_axis] = plate;
+            shape.push(make_box(ranges));
+        }
 
-impl ViewInstance for PusherView {}
-impl Drawable for PusherView {
-    fn draw(&mut self, dcf: &mut crate::gui::Dcf) {
-        self.pusher.draw(dcf);
-        self.contents
-            .draw(&mut dcf.scaled(crate::gui::Vec3::splat(0.5)));
-    }
+        shape
+    })
+});
+
+/// A block that pushes the line of blocks in front of it by one cell when
+/// [triggered](crate::world::Level::trigger) or when it becomes [powered](signal).
+///
+/// When a compressed pusher is activated, it extends: the blocks in front of it move one cell
+/// forward, and the block it held is placed in the freed cell. Pushing fails and the pusher stays
+/// compressed if the line contains an [immovable](Instance::is_movable) block, is longer than
+/// [`Self::limit`] or reaches the edge of the level. An extended pusher is immovable itself and
+/// retracts empty after [`RETRACT_DELAY`] ticks.
+#[derive(Clone)]
+pub struct Pusher {
+    /// The direction the pusher pushes in.
+    pub facing: Direction,
+
+    pub state: PusherState,
+
+    /// The largest number of blocks the pusher can move at once, between 1 and
+    /// [`MAX_PUSH_LIMIT`].
+    pub limit: u32,
+
+    /// Whether the pusher was powered when it was last updated. The pusher only extends when it
+    /// becomes powered, not while it stays powered.
+    pub powered: bool,
 }
 
-pub enum Pusher {
-    Holds(Box<Block>),
-    Extended,
+impl Pusher {
+    /// Try to extend this pusher at the position of _ctx_, pushing the line of blocks in front of
+    /// it and placing _contents_ in the freed cell. Returns whether the pusher extended.
+    fn extend(&self, contents: &Block, ctx: &mut update::Context) -> bool {
+        let step = self.facing.offset();
+        let front = ctx.pos() + step;
+
+        // Find the first empty cell in front of the pusher
+        let mut length = 0;
+        loop {
+            match ctx.level.block(front + step * length) {
+                Some(Block::Air(_)) => break,
+                Some(block) if block.is_movable() && (length as u32) < self.limit => length += 1,
+                _ => return false,
+            }
+        }
+
+        // Move the line starting from its far end so that no block is overwritten
+        for i in (0..length).rev() {
+            let block = ctx.level.block(front + step * i).unwrap().clone();
+            ctx.level.set_block(front + step * (i + 1), block);
+        }
+        ctx.level.set_block(front, contents.clone());
+
+        ctx.set_self(Block::Pusher(Self {
+            state: PusherState::Extended,
+            ..self.clone()
+        }));
+        ctx.schedule_self(RETRACT_DELAY);
+        true
+    }
 }
 
 impl Instance for Pusher {
-    type Kind = PusherKind;
-    type View = PusherView;
-
-    fn view(&self, kind: &Self::Kind, rsrc: &Resources) -> Self::View {
-        match self {
-            Self::Holds(contents) => Self::View {
-                pusher: kind.model_compressed.clone(),
-                contents: Box::new(contents.view(rsrc)),
+    /// The low byte of pusher state is one of the following:
+    /// - `0`: holds the nested block, or air if there is none,
+    /// - `1`: holds sand; legacy encoding that is never produced by [`Instance::serialize`],
+    /// - `2`: extended.
+    ///
+    /// Only state `0` may have a nested block, and at most one.
+    ///
+    /// The next byte is the [index](Direction::index) of the facing direction, and the byte after
+    /// it is the [push limit](Self::limit) minus one. The bit after them is set for
+    /// [powered](Self::powered) pushers.
+    fn from(data: &Serialized) -> Result<Self, StateError> {
+        let invalid = |reason: String| StateError::InvalidState {
+            kind: "pusher",
+            reason,
+        };
+
+        let facing = *Direction::ALL
+            .get(((data.state >> 8) & 0xFF) as usize)
+            .ok_or_else(|| invalid(format!("invalid facing in state {}", data.state)))?;
+
+        let state = match (data.state & 0xFF, &data.nested[..]) {
+            (0, []) => PusherState::Holds(Box::default()),
+            (0, [nested]) => {
+                PusherState::Holds(Box::new(Block::deserialize(&nested.kind, &nested.data)?))
+            }
+            (0, _) => return Err(invalid("pusher can hold only one block".into())),
+            (1, []) => PusherState::Holds(Box::new(Block::Sand(Sand::default()))),
+            (2, []) => PusherState::Extended,
+            (1 | 2, _) => {
+                return Err(invalid(format!(
+                    "state {} cannot have nested blocks",
+                    data.state
+                )));
+            }
+            _ => return Err(invalid(format!("invalid state {}", data.state))),
+        };
+
+        let limit = ((data.state >> 16) & 0xFF) + 1;
+        if limit > MAX_PUSH_LIMIT || data.state >> 25 != 0 {
+            return Err(invalid(format!("invalid state {}", data.state)));
+        }
+
+        Ok(Self {
+            facing,
+            state,
+            limit,
+            powered: data.state >> 24 != 0,
+        })
+    }
+
+    fn serialize(&self) -> Serialized {
+        let common = ((self.powered as u32) << 24)
+            | ((self.limit - 1) << 16)
+            | ((self.facing.index() as u32) << 8);
+        match &self.state {
+            PusherState::Holds(contents) => Serialized {
+                state: common,
+                nested: vec![contents.serialize_nested()],
             },
-            Self::Extended => Self::View {
-                pusher: kind.model_extended.clone(),
-                contents: Box::new(View::Air(AirView)),
+            PusherState::Extended => Serialized::new(common | 2),
+        }
+    }
+
+    const PROPERTIES: &'static [Property] = &[
+        Property {
+            name: "facing",
+            ty: PropertyType::Enum(&Direction::NAMES),
+        },
+        Property {
+            name: "extended",
+            ty: PropertyType::Bool,
+        },
+        Property {
+            name: "contents",
+            ty: PropertyType::Block,
+        },
+        Property {
+            name: "limit",
+            ty: PropertyType::Int(1, MAX_PUSH_LIMIT),
+        },
+        Property {
+            name: "powered",
+            ty: PropertyType::Bool,
+        },
+    ];
+
+    fn properties(&self) -> Vec<Value> {
+        let (extended, contents) = match &self.state {
+            PusherState::Holds(contents) => (false, contents.clone()),
+            PusherState::Extended => (true, Box::default()),
+        };
+
+        vec![
+            Value::Enum(self.facing.name()),
+            Value::Bool(extended),
+            Value::Block(contents),
+            Value::Int(self.limit),
+            Value::Bool(self.powered),
+        ]
+    }
+
+    fn from_properties(values: Vec<Value>) -> Result<Self, StateError> {
+        let [facing, extended, contents, limit, powered] = state::into_array(values);
+        let facing = Direction::from_name(facing.into_enum()).unwrap();
+        let contents = contents.into_block();
+
+        let state = if extended.into_bool() {
+            if !matches!(*contents, Block::Air(_)) {
+                return Err(StateError::InvalidState {
+                    kind: "pusher",
+                    reason: "an extended pusher cannot hold contents".into(),
+                });
+            }
+            PusherState::Extended
+        } else {
+            PusherState::Holds(contents)
+        };
+
+        Ok(Self {
+            facing,
+            state,
+            limit: limit.into_int(),
+            powered: powered.into_bool(),
+        })
+    }
+
+    fn physics(&self) -> Physics {
+        match self.state {
+            PusherState::Holds(_) => Physics::SOLID,
+            PusherState::Extended => Physics {
+                shape: &EXTENDED_SHAPES[self.facing.axis()],
+                ..Physics::SOLID
             },
         }
     }
 
-    fn from(data: &Serialized) -> Self {
-        match data.0 {
-            0 => Self::Holds(Box::new(Block::Air(Air))),
-            1 => Self::Holds(Box::new(Block::Sand(Sand))),
-            _ => Self::Extended,
+    fn is_movable(&self) -> bool {
+        matches!(self.state, PusherState::Holds(_))
+    }
+
+    fn update(&self, ctx: &mut update::Context) {
+        let powered = signal::is_powered(ctx.level, ctx.pos());
+        let next = Self {
+            powered,
+            ..self.clone()
+        };
+
+        match &self.state {
+            PusherState::Holds(contents) => {
+                let activated = ctx.cause() == Cause::Triggered || (powered && !self.powered);
+                if activated && next.extend(contents, ctx) {
+                    return;
+                }
+            }
+            PusherState::Extended if ctx.cause() == Cause::Scheduled => {
+                ctx.set_self(Block::Pusher(Self {
+                    state: PusherState::Holds(Box::default()),
+                    ..next
+                }));
+                return;
+            }
+            PusherState::Extended => {}
+        }
+
+        if powered != self.powered {
+            ctx.set_self(Block::Pusher(next));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::world::{IVec3, Level, Quat, UVec3, array3::Array3};
+
+    /// Create an empty level with the blocks described by _blocks_ placed along the X axis at Y of
+    /// one and Z of zero, starting from X of one.
+    fn row(blocks: &[&str]) -> Level {
+        let mut level = Level::from_blocks(
+            Array3::default(UVec3::new(8, 3, 3)),
+            Vec3::ZERO,
+            Quat::IDENTITY,
+        );
+        for (x, description) in blocks.iter().enumerate() {
+            let pos = UVec3::new(x as u32 + 1, 1, 0);
+            level.blocks[pos] = state::parse(description).unwrap();
+        }
+        level
+    }
+
+    /// Describe the blocks along the X axis at Y of one and Z of zero, from X of one to the end.
+    fn describe(level: &Level) -> Vec<String> {
+        (1..level.blocks.shape().x as i32)
+            .map(|x| level.block(IVec3::new(x, 1, 0)).unwrap().to_string())
+            .collect()
+    }
+
+    #[test]
+    fn pusher_pushes_line_and_retracts() {
+        let mut level = row(&["pusher[limit=2,contents=sand]", "sand", "pusher"]);
+        level.trigger(IVec3::new(1, 1, 0));
+        level.logic_tick(&[]);
+        assert_eq!(
+            describe(&level),
+            [
+                "pusher[extended=true,limit=2]",
+                "sand",
+                "sand",
+                "pusher",
+                "air",
+                "air",
+                "air"
+            ]
+        );
+
+        for _ in 0..RETRACT_DELAY {
+            level.logic_tick(&[]);
+        }
+        assert_eq!(describe(&level)[0], "pusher[limit=2]");
+    }
+
+    #[test]
+    fn pusher_fails_on_long_or_blocked_lines() {
+        for blocks in [
+            &["pusher[limit=2]", "sand", "sand", "sand"][..],
+            &["pusher", "pusher[extended=true]"],
+            &["pusher[facing=down]"],
+            &[
+                "pusher[limit=16]",
+                "sand",
+                "sand",
+                "sand",
+                "sand",
+                "sand",
+                "sand",
+            ],
+        ] {
+            let mut level = row(blocks);
+            let before = describe(&level);
+            level.trigger(IVec3::new(1, 1, 0));
+            level.logic_tick(&[]);
+            assert_eq!(describe(&level), before, "{blocks:?}");
         }
     }
+
+    #[test]
+    fn pusher_extends_when_it_becomes_powered() {
+        let mut level = row(&["lever[on=true]", "pusher[contents=stone]"]);
+        level.schedule_update(IVec3::new(2, 1, 0), 1);
+        level.logic_tick(&[]);
+        assert_eq!(
+            describe(&level)[1..3],
+            ["pusher[extended=true,powered=true]", "stone"]
+        );
+
+        // Staying powered does not extend the pusher again after it retracts
+        for _ in 0..RETRACT_DELAY * 2 {
+            level.logic_tick(&[]);
+        }
+        assert_eq!(describe(&level)[1..3], ["pusher[powered=true]", "stone"]);
+    }
+
+    #[test]
+    fn legacy_sand_state_is_read() {
+        let pusher = Block::deserialize("pusher", &Serialized::new(1)).unwrap();
+        assert_eq!(pusher.to_string(), "pusher[contents=sand]");
+        assert_eq!(pusher.serialize_nested().data.state, 0);
+    }
 }
@@ -0,0 +1,89 @@
+//! Blocks that shape [runs](crate::world::run): where they start and finish, where the player
+//! character [respawns](crate::world::respawn) and what kills it.
+
+use crate::content::block::*;
+
+/// A solid pad that the player character spawns on.
+///
+/// If a world contains several start blocks, the first one in the first level is used.
+#[derive(Clone)]
+pub struct Start;
+
+impl Instance for Start {
+    fn from(data: &Serialized) -> Result<Self, StateError> {
+        data.check_default("start")?;
+        Ok(Self {})
+    }
+
+    fn serialize(&self) -> Serialized {
+        Serialized::default()
+    }
+
+    fn is_movable(&self) -> bool {
+        false
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////////////////////////
+
+/// A gate that finishes the current run as soon as the player character touches its cell.
+#[derive(Clone)]
+pub struct Finish;
+
+impl Instance for Finish {
+    fn from(data: &Serialized) -> Result<Self, StateError> {
+        data.check_default("finish")?;
+        Ok(Self {})
+    }
+
+    fn serialize(&self) -> Serialized {
+        Serialized::default()
+    }
+
+    fn physics(&self) -> Physics {
+        Physics::EMPTY
+    }
+
+    fn is_movable(&self) -> bool {
+        false
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////////////////////////
+
+/// A solid pad that becomes the [respawn point](crate::world::respawn) of the player character
+/// when the character touches it.
+#[derive(Clone)]
+pub struct Checkpoint;
+
+impl Instance for Checkpoint {
+    fn from(data: &Serialized) -> Result<Self, StateError> {
+        data.check_default("checkpoint")?;
+        Ok(Self {})
+    }
+
+    fn serialize(&self) -> Serialized {
+        Serialized::default()
+    }
+
+    fn is_movable(&self) -> bool {
+        false
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////////////////////////
+
+/// A solid block that kills the player character when the character touches it.
+#[derive(Clone)]
+pub struct Hazard;
+
+impl Instance for Hazard {
+    fn from(data: &Serialized) -> Result<Self, StateError> {
+        data.check_default("hazard")?;
+        Ok(Self {})
+    }
+
+    fn serialize(&self) -> Serialized {
+        Serialized::default()
+    }
+}
@@ -0,0 +1,144 @@
+//! Sand: a block that falls when nothing supports it.
+
+use crate::{
+    content::block::{state::PropertyType, *},
+    world::{Direction, update},
+};
+
+/// A block that falls down one cell per logic tick while the block below it is air.
+///
+/// Sand starts falling when it is [updated](crate::world::update) for any reason and the block
+/// below it is air, and lands as soon as it reaches any other block or the edge of the level. Sand
+/// never replaces blocks, so it rests on non-solid blocks such as levers, wires or finish blocks.
+///
+/// Sand in the initial state of a level receives an update in the first logic tick, so sand that
+/// nothing supports falls as soon as the level is loaded or reset.
+#[derive(Clone, Default)]
+pub struct Sand {
+    /// Whether the sand moved down one cell in the last logic tick it was updated in and has not
+    /// landed yet.
+    pub falling: bool,
+}
+
+impl Instance for Sand {
+    /// Sand state is `0` for resting and `1` for falling sand.
+    fn from(data: &Serialized) -> Result<Self, StateError> {
+        Ok(Self {
+            falling: data.check_flag("sand")?,
+        })
+    }
+
+    fn serialize(&self) -> Serialized {
+        Serialized::new(self.falling as u32)
+    }
+
+    const PROPERTIES: &'static [Property] = &[Property {
+        name: "falling",
+        ty: PropertyType::Bool,
+    }];
+
+    fn properties(&self) -> Vec<Value> {
+        vec![Value::Bool(self.falling)]
+    }
+
+    fn from_properties(values: Vec<Value>) -> Result<Self, StateError> {
+        let [falling] = state::into_array(values);
+        Ok(Self {
+            falling: falling.into_bool(),
+        })
+    }
+
+    fn physics(&self) -> Physics {
+        Physics {
+            friction: 0.8,
+            ..Physics::SOLID
+        }
+    }
+
+    fn update(&self, ctx: &mut update::Context) {
+        let below = ctx.pos() + Direction::Down.offset();
+
+        if matches!(ctx.level.block(below), Some(Block::Air(_))) {
+            // The sand placed below receives an update in the next tick and keeps falling
+            ctx.set_self(Block::Air(Air));
+            ctx.level
+                .set_block(below, Block::Sand(Self { falling: true }));
+        } else if self.falling {
+            ctx.set_self(Block::Sand(Self { falling: false }));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        logic::Logic,
+        world::{Event, IVec3, Level, Quat, UVec3, Vec3, World, array3::Array3},
+    };
+
+    fn empty_level() -> Level {
+        Level::from_blocks(
+            Array3::default(UVec3::new(4, 4, 6)),
+            Vec3::ZERO,
+            Quat::IDENTITY,
+        )
+    }
+
+    fn describe(level: &Level, pos: IVec3) -> String {
+        level.block(pos).unwrap().to_string()
+    }
+
+    #[test]
+    fn sand_falls_one_cell_per_tick_and_lands() {
+        let mut level = empty_level();
+        level.set_block(IVec3::new(1, 1, 4), Block::Sand(Sand::default()));
+        level.set_block(IVec3::new(1, 1, 1), Block::Stone(Stone));
+
+        for z in [3, 2] {
+            level.logic_tick(&[]);
+            assert_eq!(describe(&level, IVec3::new(1, 1, z)), "sand[falling=true]");
+            assert_eq!(describe(&level, IVec3::new(1, 1, z + 1)), "air");
+        }
+
+        level.logic_tick(&[]);
+        assert_eq!(describe(&level, IVec3::new(1, 1, 2)), "sand");
+        level.logic_tick(&[]);
+        assert!(level.updates.is_empty());
+    }
+
+    #[test]
+    fn sand_rests_on_non_solid_blocks_and_level_floor() {
+        let mut level = empty_level();
+        level.set_block(IVec3::new(1, 1, 2), Block::Sand(Sand::default()));
+        level.set_block(IVec3::new(1, 1, 1), state::parse("lever").unwrap());
+        level.set_block(IVec3::new(2, 2, 0), Block::Sand(Sand::default()));
+
+        for _ in 0..3 {
+            level.logic_tick(&[]);
+        }
+        assert_eq!(describe(&level, IVec3::new(1, 1, 2)), "sand");
+        assert_eq!(describe(&level, IVec3::new(1, 1, 1)), "lever");
+        assert_eq!(describe(&level, IVec3::new(2, 2, 0)), "sand");
+        assert!(level.updates.is_empty());
+    }
+
+    #[test]
+    fn unsupported_sand_falls_after_load_and_reset() {
+        let mut level = empty_level();
+        level.blocks[UVec3::new(2, 2, 4)] = Block::Sand(Sand::default());
+        level.save_initial_state();
+
+        let logic = Logic::new();
+        let mut world = World::from_levels(vec![level]);
+        world.process(Event::LogicTick, &logic);
+        let level = &mut world.levels[0];
+        assert_eq!(describe(level, IVec3::new(2, 2, 3)), "sand[falling=true]");
+
+        level.reset();
+        assert_eq!(describe(level, IVec3::new(2, 2, 4)), "sand");
+        world.process(Event::LogicTick, &logic);
+        let level = &world.levels[0];
+        assert_eq!(describe(level, IVec3::new(2, 2, 3)), "sand[falling=true]");
+    }
+}
@@ -0,0 +1,574 @@
+//! Blocks that emit, conduct and consume [signals](crate::world::signal).
+
+use crate::{
+    content::block::{state::PropertyType, *},
+    world::{
+        Direction, Float, Vec3,
+        collision::Aabb,
+        signal::{self, MAX_POWER, Power},
+        update::{self, Cause},
+    },
+};
+
+/// Number of logic ticks a [`Button`] stays pressed.
+pub const BUTTON_DURATION: u64 = 20;
+
+/// The largest allowed [delay](Relay::delay) of a relay.
+pub const MAX_RELAY_DELAY: u32 = 40;
+
+/// Get the power a source emits when _active_.
+fn source_power(active: bool) -> Power {
+    if active { MAX_POWER } else { 0 }
+}
+
+////////////////////////////////////////////////////////////////////////////////////////////////////
+
+/// A source that toggles between on and off every time it is
+/// [triggered](crate::world::Level::trigger).
+#[derive(Clone)]
+pub struct Lever {
+    /// Whether the lever emits power.
+    pub on: bool,
+}
+
+impl Instance for Lever {
+    fn from(data: &Serialized) -> Result<Self, StateError> {
+        Ok(Self {
+            on: data.check_flag("lever")?,
+        })
+    }
+
+    fn serialize(&self) -> Serialized {
+        Serialized::new(self.on as u32)
+    }
+
+    const PROPERTIES: &'static [Property] = &[Property {
+        name: "on",
+        ty: PropertyType::Bool,
+    }];
+
+    fn properties(&self) -> Vec<Value> {
+        vec![Value::Bool(self.on)]
+    }
+
+    fn from_properties(values: Vec<Value>) -> Result<Self, StateError> {
+        let [on] = state::into_array(values);
+        Ok(Self { on: on.into_bool() })
+    }
+
+    fn physics(&self) -> Physics {
+        Physics::EMPTY
+    }
+
+    fn signal_output(&self, _direction: Direction) -> Power {
+        source_power(self.on)
+    }
+
+    fn update(&self, ctx: &mut update::Context) {
+        if ctx.cause() == Cause::Triggered {
+            ctx.set_self(Block::Lever(Self { on: !self.on }));
+        }
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////////////////////////
+
+/// A source that emits power for [`BUTTON_DURATION`] ticks after it is
+/// [triggered](crate::world::Level::trigger).
+#[derive(Clone)]
+pub struct Button {
+    /// Whether the button is pressed and emits power.
+    pub pressed: bool,
+}
+
+impl Instance for Button {
+    fn from(data: &Serialized) -> Result<Self, StateError> {
+        Ok(Self {
+            pressed: data.check_flag("button")?,
+        })
+    }
+
+    fn serialize(&self) -> Serialized {
+        Serialized::new(self.pressed as u32)
+    }
+
+    const PROPERTIES: &'static [Property] = &[Property {
+        name: "pressed",
+        ty: PropertyType::Bool,
+    }];
+
+    fn properties(&self) -> Vec<Value> {
+        vec![Value::Bool(self.pressed)]
+    }
+
+    fn from_properties(values: Vec<Value>) -> Result<Self, StateError> {
+        let [pressed] = state::into_array(values);
+        Ok(Self {
+            pressed: pressed.into_bool(),
+        })
+    }
+
+    fn physics(&self) -> Physics {
+        Physics::EMPTY
+    }
+
+    fn signal_output(&self, _direction: Direction) -> Power {
+        source_power(self.pressed)
+    }
+
+    fn update(&self, ctx: &mut update::Context) {
+        match ctx.cause() {
+            Cause::Triggered if !self.pressed => {
+                ctx.set_self(Block::Button(Self { pressed: true }));
+                ctx.schedule_self(BUTTON_DURATION);
+            }
+            Cause::Scheduled if self.pressed => {
+                ctx.set_self(Block::Button(Self { pressed: false }));
+            }
+            _ => {}
+        }
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////////////////////////
+
+/// A thin slab on the floor that is a source while a body such as the player character stands on
+/// it.
+#[derive(Clone)]
+pub struct Plate {
+    /// Whether a body stands on the plate, so that it emits power.
+    pub pressed: bool,
+}
+
+impl Plate {
+    /// Height of the slab.
+    const HEIGHT: Float = 1.0 / 16.0;
+
+    /// The collision shape of plates.
+    const SHAPE: &'static [Aabb] = &[Aabb {
+        min: Vec3::splat(-0.5),
+        max: Vec3::new(0.5, 0.5, -0.5 + Self::HEIGHT),
+    }];
+}
+
+impl Instance for Plate {
+    fn from(data: &Serialized) -> Result<Self, StateError> {
+        Ok(Self {
+            pressed: data.check_flag("plate")?,
+        })
+    }
+
+    fn serialize(&self) -> Serialized {
+        Serialized::new(self.pressed as u32)
+    }
+
+    const PROPERTIES: &'static [Property] = &[Property {
+        name: "pressed",
+        ty: PropertyType::Bool,
+    }];
+
+    fn properties(&self) -> Vec<Value> {
+        vec![Value::Bool(self.pressed)]
+    }
+
+    fn from_properties(values: Vec<Value>) -> Result<Self, StateError> {
+        let [pressed] = state::into_array(values);
+        Ok(Self {
+            pressed: pressed.into_bool(),
+        })
+    }
+
+    fn physics(&self) -> Physics {
+        Physics {
+            shape: Self::SHAPE,
+            ..Physics::SOLID
+        }
+    }
+
+    fn signal_output(&self, _direction: Direction) -> Power {
+        source_power(self.pressed)
+    }
+
+    fn update(&self, ctx: &mut update::Context) {
+        let pressed = ctx.is_occupied();
+        if pressed != self.pressed {
+            ctx.set_self(Block::Plate(Self { pressed }));
+        }
+
+        // Occupied cells are only updated while they are occupied; check for release explicitly
+        if pressed {
+            ctx.schedule_self(1);
+        }
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////////////////////////
+
+/// A conductor that carries the strongest power of its neighbours minus one.
+///
+/// Wires conduct in all directions and take one logic tick to notice a change in their neighbours.
+/// Wires that only feed each other count their power down until they run out.
+#[derive(Clone)]
+pub struct Wire {
+    /// The power the wire carries and emits.
+    pub power: Power,
+}
+
+impl Instance for Wire {
+    fn from(data: &Serialized) -> Result<Self, StateError> {
+        if !data.nested.is_empty() || data.state > MAX_POWER as u32 {
+            return Err(StateError::InvalidState {
+                kind: "wire",
+                reason: format!("invalid state {data:?}"),
+            });
+        }
+        Ok(Self {
+            power: data.state as Power,
+        })
+    }
+
+    fn serialize(&self) -> Serialized {
+        Serialized::new(self.power as u32)
+    }
+
+    const PROPERTIES: &'static [Property] = &[Property {
+        name: "power",
+        ty: PropertyType::Int(0, MAX_POWER as u32),
+    }];
+
+    fn properties(&self) -> Vec<Value> {
+        vec![Value::Int(self.power as u32)]
+    }
+
+    fn from_properties(values: Vec<Value>) -> Result<Self, StateError> {
+        let [power] = state::into_array(values);
+        Ok(Self {
+            power: power.into_int() as Power,
+        })
+    }
+
+    fn physics(&self) -> Physics {
+        Physics::EMPTY
+    }
+
+    fn signal_output(&self, _direction: Direction) -> Power {
+        self.power
+    }
+
+    fn update(&self, ctx: &mut update::Context) {
+        // Power changes straight to its new value, even when it drops, so that consumers fed by
+        // several sources stay powered while one of them turns off
+        let power = signal::input(ctx.level, ctx.pos()).saturating_sub(1);
+        if power != self.power {
+            ctx.set_self(Block::Wire(Self { power }));
+        }
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////////////////////////
+
+/// A conductor that passes a signal on in one direction only, after a configurable delay.
+///
+/// A relay is powered by the block behind it and emits [`MAX_POWER`] towards the block in front of
+/// it. It adopts a change of its input [`Self::delay`] logic ticks after noticing it, provided the
+/// input has not changed back by then; shorter pulses are swallowed. Since relays do not conduct
+/// backwards, delayed signals cannot echo back into the wires that feed them.
+#[derive(Clone)]
+pub struct Relay {
+    /// The direction the relay emits power in.
+    pub facing: Direction,
+
+    /// Propagation delay in logic ticks, between 1 and [`MAX_RELAY_DELAY`].
+    pub delay: u32,
+
+    /// Whether the relay emits power.
+    pub powered: bool,
+}
+
+impl Instance for Relay {
+    /// The low byte of relay state is `1` for [powered](Self::powered) relays and `0` otherwise.
+    /// The next byte is the [index](Direction::index) of the facing direction, and the byte after
+    /// it is the [delay](Self::delay) minus one.
+    fn from(data: &Serialized) -> Result<Self, StateError> {
+        let invalid = || StateError::InvalidState {
+            kind: "relay",
+            reason: format!("invalid state {data:?}"),
+        };
+
+        let facing = *Direction::ALL
+            .get(((data.state >> 8) & 0xFF) as usize)
+            .ok_or_else(invalid)?;
+        let delay = (data.state >> 16) + 1;
+        if !data.nested.is_empty() || data.state & 0xFF > 1 || delay > MAX_RELAY_DELAY {
+            return Err(invalid());
+        }
+
+        Ok(Self {
+            facing,
+            delay,
+            powered: data.state & 1 != 0,
+        })
+    }
+
+    fn serialize(&self) -> Serialized {
+        Serialized::new(
+            ((self.delay - 1) << 16) | ((self.facing.index() as u32) << 8) | self.powered as u32,
+        )
+    }
+
+    const PROPERTIES: &'static [Property] = &[
+        Property {
+            name: "facing",
+            ty: PropertyType::Enum(&Direction::NAMES),
+        },
+        Property {
+            name: "delay",
+            ty: PropertyType::Int(1, MAX_RELAY_DELAY),
+        },
+        Property {
+            name: "powered",
+            ty: PropertyType::Bool,
+        },
+    ];
+
+    fn properties(&self) -> Vec<Value> {
+        vec![
+            Value::Enum(self.facing.name()),
+            Value::Int(self.delay),
+            Value::Bool(self.powered),
+        ]
+    }
+
+    fn from_properties(values: Vec<Value>) -> Result<Self, StateError> {
+        let [facing, delay, powered] = state::into_array(values);
+        Ok(Self {
+            facing: Direction::from_name(facing.into_enum()).unwrap(),
+            delay: delay.into_int(),
+            powered: powered.into_bool(),
+        })
+    }
+
+    fn physics(&self) -> Physics {
+        Physics::EMPTY
+    }
+
+    fn signal_output(&self, direction: Direction) -> Power {
+        if direction == self.facing {
+            source_power(self.powered)
+        } else {
+            0
+        }
+    }
+
+    fn update(&self, ctx: &mut update::Context) {
+        let behind = self.facing.opposite();
+        let powered = ctx
+            .neighbour(behind)
+            .is_some_and(|block| block.signal_output(self.facing) > 0);
+        if powered == self.powered {
+            return;
+        }
+
+        if ctx.cause() == Cause::Scheduled {
+            ctx.set_self(Block::Relay(Self {
+                powered,
+                ..self.clone()
+            }));
+        } else {
+            ctx.schedule_self(self.delay as u64);
+        }
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////////////////////////
+
+/// A consumer that is open and lets bodies through while it is powered.
+#[derive(Clone)]
+pub struct Door {
+    /// Whether the door is open.
+    pub open: bool,
+}
+
+impl Instance for Door {
+    fn from(data: &Serialized) -> Result<Self, StateError> {
+        Ok(Self {
+            open: data.check_flag("door")?,
+        })
+    }
+
+    fn serialize(&self) -> Serialized {
+        Serialized::new(self.open as u32)
+    }
+
+    const PROPERTIES: &'static [Property] = &[Property {
+        name: "open",
+        ty: PropertyType::Bool,
+    }];
+
+    fn properties(&self) -> Vec<Value> {
+        vec![Value::Bool(self.open)]
+    }
+
+    fn from_properties(values: Vec<Value>) -> Result<Self, StateError> {
+        let [open] = state::into_array(values);
+        Ok(Self {
+            open: open.into_bool(),
+        })
+    }
+
+    fn physics(&self) -> Physics {
+        if self.open {
+            Physics::EMPTY
+        } else {
+            Physics::SOLID
+        }
+    }
+
+    fn update(&self, ctx: &mut update::Context) {
+        let open = signal::is_powered(ctx.level, ctx.pos());
+        if open != self.open {
+            ctx.set_self(Block::Door(Self { open }));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::world::{IVec3, Level, Quat, UVec3, array3::Array3};
+
+    fn wire_power(level: &Level, pos: IVec3) -> Power {
+        match level.block(pos) {
+            Some(Block::Wire(wire)) => wire.power,
+            _ => panic!("no wire at {pos}"),
+        }
+    }
+
+    fn is_open(level: &Level, pos: IVec3) -> bool {
+        matches!(level.block(pos), Some(Block::Door(Door { open: true })))
+    }
+
+    /// A wire fed by two levers must not lose power when one of them turns off, or the door it
+    /// powers would close and reopen.
+    #[test]
+    fn wire_stays_powered_when_one_of_two_sources_turns_off() {
+        let blocks = Array3::default(UVec3::new(8, 3, 1));
+        let mut level = Level::from_blocks(blocks, Vec3::ZERO, Quat::IDENTITY);
+        let at = |x| IVec3::new(x, 1, 0);
+        let door = IVec3::new(2, 2, 0);
+
+        level.set_block(at(0), Block::Lever(Lever { on: true }));
+        for x in 1..6 {
+            level.set_block(at(x), Block::Wire(Wire { power: 0 }));
+        }
+        level.set_block(at(6), Block::Lever(Lever { on: true }));
+        level.set_block(door, Block::Door(Door { open: false }));
+        for _ in 0..10 {
+            level.logic_tick(&[]);
+        }
+        assert!(is_open(&level, door));
+        assert_eq!(wire_power(&level, at(1)), MAX_POWER - 1);
+
+        level.trigger(at(0));
+        for _ in 0..20 {
+            level.logic_tick(&[]);
+            assert!(wire_power(&level, at(2)) > 0);
+            assert!(is_open(&level, door));
+        }
+        assert_eq!(wire_power(&level, at(1)), MAX_POWER - 5);
+        assert!(level.updates.is_empty());
+    }
+
+    fn empty_level() -> Level {
+        Level::from_blocks(
+            Array3::default(UVec3::new(8, 4, 1)),
+            Vec3::ZERO,
+            Quat::IDENTITY,
+        )
+    }
+
+    fn ticks(level: &mut Level, count: usize) {
+        for _ in 0..count {
+            level.logic_tick(&[]);
+        }
+    }
+
+    /// A relay passes the signal of a lever on to a door only after its delay.
+    #[test]
+    fn relay_delays_signal() {
+        let mut level = empty_level();
+        let at = |x| IVec3::new(x, 1, 0);
+        level.set_block(at(0), Block::Lever(Lever { on: false }));
+        level.set_block(at(1), Block::Wire(Wire { power: 0 }));
+        level.set_block(at(2), state::parse("relay[facing=east,delay=5]").unwrap());
+        level.set_block(at(3), Block::Door(Door { open: false }));
+        ticks(&mut level, 3);
+        assert!(level.updates.is_empty());
+
+        level.trigger(at(0));
+        let mut elapsed = 0;
+        while !is_open(&level, at(3)) {
+            level.logic_tick(&[]);
+            elapsed += 1;
+            assert!(elapsed < 20, "door did not open");
+        }
+        assert!(elapsed > 5, "door opened after {elapsed} ticks");
+        assert_eq!(wire_power(&level, at(1)), MAX_POWER - 1);
+
+        level.trigger(at(0));
+        ticks(&mut level, 20);
+        assert!(!is_open(&level, at(3)));
+        assert_eq!(wire_power(&level, at(1)), 0);
+        assert!(level.updates.is_empty());
+    }
+
+    /// Wires that only feed each other lose their power once the button that fed them releases.
+    #[test]
+    fn wire_loop_runs_out_of_power() {
+        let mut level = empty_level();
+        let button = IVec3::new(0, 1, 0);
+        let wires = [(1, 1), (2, 1), (2, 2), (1, 2)].map(|(x, y)| IVec3::new(x, y, 0));
+        level.set_block(button, Block::Button(Button { pressed: false }));
+        for pos in wires {
+            level.set_block(pos, Block::Wire(Wire { power: 0 }));
+        }
+
+        level.trigger(button);
+        ticks(&mut level, 5);
+        assert_eq!(wire_power(&level, wires[0]), MAX_POWER - 1);
+
+        ticks(
+            &mut level,
+            BUTTON_DURATION as usize + 2 * MAX_POWER as usize,
+        );
+        for pos in wires {
+            assert_eq!(wire_power(&level, pos), 0);
+        }
+        assert!(level.updates.is_empty());
+    }
+
+    #[test]
+    fn plate_is_pressed_while_occupied() {
+        let mut level = empty_level();
+        let plate = IVec3::new(5, 1, 0);
+        let door = plate + IVec3::X;
+        level.set_block(plate, Block::Plate(Plate { pressed: false }));
+        level.set_block(door, Block::Door(Door { open: false }));
+
+        for _ in 0..3 {
+            level.logic_tick(&[plate]);
+        }
+        assert!(matches!(
+            level.block(plate),
+            Some(Block::Plate(Plate { pressed: true }))
+        ));
+        assert!(is_open(&level, door));
+
+        ticks(&mut level, 3);
+        assert!(matches!(
+            level.block(plate),
+            Some(Block::Plate(Plate { pressed: false }))
+        ));
+        assert!(!is_open(&level, door));
+    }
+}
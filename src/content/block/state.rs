@@ -0,0 +1,624 @@
+//! Structured block states with named properties.
+//!
+//! Each block kind declares its [properties](Property) via [`Instance::PROPERTIES`]. A block can be
+//! described by its kind and property values with a string such as
+//! `pusher[facing=north,contents=sand]`:
+//! - the kind name comes first, as accepted by [`KindId::from_name`],
+//! - property assignments `<name>=<value>` follow in square brackets, separated by commas; the
+//!   brackets are omitted if no properties are assigned,
+//! - properties that are not assigned take their [default values](PropertyType::default_value),
+//! - values of [block properties](PropertyType::Block) are block descriptions themselves, nested at
+//!   most [`MAX_NESTING_DEPTH`] deep.
+//!
+//! See [`parse`] and the [`Display`](std::fmt::Display) implementation of [`Block`].
+
+use crate::content::block::*;
+
+/// The type of values that a [`Property`] may take.
+pub enum PropertyType {
+    /// `true` or `false`. Defaults to `false`.
+    Bool,
+
+    /// One of the listed lowercase names. Defaults to the first name.
+    Enum(&'static [&'static str]),
+
+    /// An integer between the bounds (inclusive). Defaults to the lower bound.
+    Int(u32, u32),
+
+    /// A block. Defaults to air.
+    Block,
+}
+
+impl PropertyType {
+    /// Get the value that properties of this type take when not assigned explicitly.
+    pub fn default_value(&self) -> Value {
+        match self {
+            Self::Bool => Value::Bool(false),
+            Self::Enum(names) => Value::Enum(names[0]),
+            Self::Int(min, _) => Value::Int(*min),
+            Self::Block => Value::Block(Box::default()),
+        }
+    }
+
+    /// Check whether _value_ is the [default value](Self::default_value) of this type.
+    fn is_default(&self, value: &Value) -> bool {
+        match (self, value) {
+            (Self::Block, Value::Block(block)) => matches!(**block, Block::Air(_)),
+            (_, value) => self.default_value().to_string() == value.to_string(),
+        }
+    }
+
+    /// Describe the values allowed by this type for error messages.
+    fn describe(&self) -> String {
+        match self {
+            Self::Bool => "true or false".into(),
+            Self::Enum(names) => format!("one of {}", names.join(", ")),
+            Self::Int(min, max) => format!("an integer from {min} to {max}"),
+            Self::Block => "a block".into(),
+        }
+    }
+}
+
+/// A named property of a block kind, such as the facing direction of a pusher.
+pub struct Property {
+    /// The lowercase name of the property.
+    pub name: &'static str,
+
+    /// The type of values this property may take.
+    pub ty: PropertyType,
+}
+
+/// A value of a [`Property`] that is known to match its [type](PropertyType).
+pub enum Value {
+    /// A value of a [`PropertyType::Bool`] property.
+    Bool(bool),
+
+    /// A value of a [`PropertyType::Enum`] property; always one of the declared names.
+    Enum(&'static str),
+
+    /// A value of a [`PropertyType::Int`] property; always within the declared bounds.
+    Int(u32),
+
+    /// A value of a [`PropertyType::Block`] property.
+    Block(Box<Block>),
+}
+
+impl Value {
+    /// Unwrap a [`Value::Bool`].
+    ///
+    /// # Panics
+    /// Panics if `self` is of another type.
+    pub fn into_bool(self) -> bool {
+        match self {
+            Self::Bool(value) => value,
+            _ => panic!("Value should be a bool"),
+        }
+    }
+
+    /// Unwrap a [`Value::Enum`].
+    ///
+    /// # Panics
+    /// Panics if `self` is of another type.
+    pub fn into_enum(self) -> &'static str {
+        match self {
+            Self::Enum(name) => name,
+            _ => panic!("Value should be an enum"),
+        }
+    }
+
+    /// Unwrap a [`Value::Int`].
+    ///
+    /// # Panics
+    /// Panics if `self` is of another type.
+    pub fn into_int(self) -> u32 {
+        match self {
+            Self::Int(value) => value,
+            _ => panic!("Value should be an integer"),
+        }
+    }
+
+    /// Unwrap a [`Value::Block`].
+    ///
+    /// # Panics
+    /// Panics if `self` is of another type.
+    pub fn into_block(self) -> Box<Block> {
+        match self {
+            Self::Block(block) => block,
+            _ => panic!("Value should be a block"),
+        }
+    }
+}
+
+/// Convert property values passed to [`Instance::from_properties`] into an array for
+/// destructuring.
+///
+/// # Panics
+/// Panics if the number of values is not _N_.
+pub fn into_array<const N: usize>(values: Vec<Value>) -> [Value; N] {
+    let len = values.len();
+    values
+        .try_into()
+        .unwrap_or_else(|_| panic!("Expected {N} property values, got {len}"))
+}
+
+impl std::fmt::Display for Value {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Bool(value) => write!(f, "{value}"),
+            Self::Enum(name) => write!(f, "{name}"),
+            Self::Int(value) => write!(f, "{value}"),
+            Self::Block(block) => write!(f, "{block}"),
+        }
+    }
+}
+
+/// An error that might occur when constructing a block from its state.
+#[derive(Debug)]
+pub enum StateError {
+    /// No block kind has the given name.
+    UnknownKind {
+        /// The name that was not found.
+        kind: String,
+    },
+
+    /// The kind does not declare a property with the given name.
+    UnknownProperty {
+        /// The name of the block kind.
+        kind: &'static str,
+        /// The name that was not found.
+        property: String,
+    },
+
+    /// A property was assigned more than once.
+    DuplicateProperty {
+        /// The name of the block kind.
+        kind: &'static str,
+        /// The name of the property.
+        property: &'static str,
+    },
+
+    /// A property value does not match the type of the property or is out of range.
+    InvalidValue {
+        /// The name of the block kind.
+        kind: &'static str,
+        /// The name of the property.
+        property: &'static str,
+        /// The offending value.
+        value: String,
+        /// A description of allowed values.
+        expected: String,
+    },
+
+    /// The state is not valid for the kind, e.g. a combination of values is contradictory or
+    /// [serialized](Serialized) data is out of range.
+    InvalidState {
+        /// The name of the block kind.
+        kind: &'static str,
+        /// A human-readable description of the problem.
+        reason: String,
+    },
+
+    /// The description of the state is not well-formed.
+    Syntax {
+        /// A human-readable description of the problem.
+        reason: String,
+    },
+}
+
+impl std::fmt::Display for StateError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::UnknownKind { kind } => write!(f, "unknown block kind {kind:?}"),
+            Self::UnknownProperty { kind, property } => {
+                write!(f, "block kind {kind:?} has no property {property:?}")
+            }
+            Self::DuplicateProperty { kind, property } => {
+                write!(
+                    f,
+                    "property {property:?} of {kind:?} is assigned more than once"
+                )
+            }
+            Self::InvalidValue {
+                kind,
+                property,
+                value,
+                expected,
+            } => write!(
+                f,
+                "invalid value {value:?} for property {property:?} of {kind:?}, expected {expected}"
+            ),
+            Self::InvalidState { kind, reason } => write!(f, "invalid state of {kind:?}: {reason}"),
+            Self::Syntax { reason } => write!(f, "{reason}"),
+        }
+    }
+}
+
+impl std::error::Error for StateError {}
+
+/// A [`StateError`] that occurred while parsing a block description, with its location.
+#[derive(Debug)]
+pub struct ParseError {
+    /// The 0-based index of the character in the input at which the problem was found.
+    pub position: usize,
+
+    /// The problem.
+    pub error: StateError,
+}
+
+impl std::fmt::Display for ParseError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "at character {}: {}", self.position + 1, self.error)
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+/// Parse a block description such as `pusher[facing=north,contents=sand]`.
+///
+/// See [module description](self) for syntax. Whitespace is not allowed.
+pub fn parse(input: &str) -> Result<Block, ParseError> {
+    let mut parser = Parser {
+        chars: input.chars().collect(),
+        position: 0,
+    };
+
+    let block = parser.block(0)?;
+    if parser.position < parser.chars.len() {
+        return Err(parser.error(format!(
+            "unexpected character {:?}",
+            parser.chars[parser.position]
+        )));
+    }
+
+    Ok(block)
+}
+
+/// Recursive descent parser of block descriptions.
+struct Parser {
+    chars: Vec<char>,
+
+    /// Index of the next character in `chars`.
+    position: usize,
+}
+
+impl Parser {
+    /// Create a [`StateError::Syntax`] at current position.
+    fn error(&self, reason: impl Into<String>) -> ParseError {
+        self.at(
+            self.position,
+            StateError::Syntax {
+                reason: reason.into(),
+            },
+        )
+    }
+
+    /// Locate _error_ at given _position_.
+    fn at(&self, position: usize, error: StateError) -> ParseError {
+        ParseError { position, error }
+    }
+
+    /// Consume the next character if it is _expected_.
+    fn eat(&mut self, expected: char) -> bool {
+        if self.chars.get(self.position) == Some(&expected) {
+            self.position += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    /// Consume an identifier or a number: a possibly empty sequence of lowercase letters, digits
+    /// and underscores.
+    fn word(&mut self) -> String {
+        let start = self.position;
+        while self
+            .chars
+            .get(self.position)
+            .is_some_and(|c| matches!(c, 'a'..='z' | '0'..='9' | '_'))
+        {
+            self.position += 1;
+        }
+        self.chars[start..self.position].iter().collect()
+    }
+
+    /// Parse a complete block description nested in _depth_ other blocks.
+    fn block(&mut self, depth: usize) -> Result<Block, ParseError> {
+        if depth > MAX_NESTING_DEPTH {
+            return Err(self.error("blocks are nested too deeply"));
+        }
+
+        let kind_position = self.position;
+        let kind = self.word();
+        if kind.is_empty() {
+            return Err(self.error("expected block kind"));
+        }
+
+        let properties =
+            Block::properties_of(&kind).map_err(|error| self.at(kind_position, error))?;
+        let kind = Block::canonical_name(&kind).unwrap();
+
+        let mut values: Vec<Option<Value>> = properties.iter().map(|_| None).collect();
+
+        if self.eat('[') {
+            loop {
+                let name_position = self.position;
+                let name = self.word();
+                if name.is_empty() {
+                    return Err(self.error("expected property name"));
+                }
+
+                let Some(index) = properties.iter().position(|p| p.name == name) else {
+                    return Err(self.at(
+                        name_position,
+                        StateError::UnknownProperty {
+                            kind,
+                            property: name,
+                        },
+                    ));
+                };
+                let property = &properties[index];
+
+                if values[index].is_some() {
+                    return Err(self.at(
+                        name_position,
+                        StateError::DuplicateProperty {
+                            kind,
+                            property: property.name,
+                        },
+                    ));
+                }
+
+                if !self.eat('=') {
+                    return Err(self.error("expected '='"));
+                }
+
+                let value_position = self.position;
+                let value = if let PropertyType::Block = property.ty {
+                    Value::Block(Box::new(self.block(depth + 1)?))
+                } else {
+                    let word = self.word();
+                    parse_value(kind, property, &word).map_err(|e| self.at(value_position, e))?
+                };
+                values[index] = Some(value);
+
+                if self.eat(']') {
+                    break;
+                }
+                if !self.eat(',') {
+                    return Err(self.error("expected ',' or ']'"));
+                }
+            }
+        }
+
+        let values = values
+            .into_iter()
+            .zip(properties)
+            .map(|(value, property)| value.unwrap_or_else(|| property.ty.default_value()))
+            .collect();
+
+        Block::from_properties(kind, values).map_err(|error| self.at(kind_position, error))
+    }
+}
+
+/// Parse the value of a non-block _property_ of _kind_ from _word_.
+fn parse_value(kind: &'static str, property: &Property, word: &str) -> Result<Value, StateError> {
+    let value = match &property.ty {
+        PropertyType::Bool => match word {
+            "true" => Some(Value::Bool(true)),
+            "false" => Some(Value::Bool(false)),
+            _ => None,
+        },
+        PropertyType::Enum(names) => names
+            .iter()
+            .find(|name| **name == word)
+            .map(|name| Value::Enum(name)),
+        PropertyType::Int(min, max) => word
+            .parse()
+            .ok()
+            .filter(|value| (min..=max).contains(&value))
+            .map(Value::Int),
+        PropertyType::Block => unreachable!("Block properties are parsed as blocks"),
+    };
+
+    value.ok_or_else(|| StateError::InvalidValue {
+        kind,
+        property: property.name,
+        value: word.into(),
+        expected: property.ty.describe(),
+    })
+}
+
+/// Writes the description of this block that [`parse`] accepts, omitting properties with default
+/// values.
+impl std::fmt::Display for Block {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.kind_name())?;
+
+        let mut first = true;
+        for (property, value) in self.kind_properties().iter().zip(self.properties()) {
+            if property.ty.is_default(&value) {
+                continue;
+            }
+            write!(
+                f,
+                "{}{}={}",
+                if first { "[" } else { "," },
+                property.name,
+                value
+            )?;
+            first = false;
+        }
+
+        if !first {
+            write!(f, "]")?;
+        }
+        Ok(())
+    }
+}
+
+/// List all valid states of the block kind called _kind_.
+///
+/// Every combination of property values is tried. Values of [block
+/// properties](PropertyType::Block) are limited to the default state of every kind, so nesting is
+/// never more than one level deep. Combinations rejected by the kind are skipped.
+pub fn all_states(kind: &str) -> Result<Vec<Block>, StateError> {
+    let properties = Block::properties_of(kind)?;
+    let kind = Block::canonical_name(kind).unwrap();
+
+    // Number of possible values of each property
+    let counts: Vec<usize> = properties
+        .iter()
+        .map(|property| match &property.ty {
+            PropertyType::Bool => 2,
+            PropertyType::Enum(names) => names.len(),
+            PropertyType::Int(min, max) => (max - min) as usize + 1,
+            PropertyType::Block => KindId::COUNT,
+        })
+        .collect();
+
+    let value = |ty: &PropertyType, index: usize| match ty {
+        PropertyType::Bool => Value::Bool(index == 1),
+        PropertyType::Enum(names) => Value::Enum(names[index]),
+        PropertyType::Int(min, _) => Value::Int(min + index as u32),
+        PropertyType::Block => Value::Block(Box::new(default_state(
+            KindId::new(index as u16).unwrap().name(),
+        ))),
+    };
+
+    let mut result = Vec::new();
+    let mut indices = vec![0; properties.len()];
+    loop {
+        let values = properties
+            .iter()
+            .zip(&indices)
+            .map(|(property, index)| value(&property.ty, *index))
+            .collect();
+        if let Ok(block) = Block::from_properties(kind, values) {
+            result.push(block);
+        }
+
+        // Advance indices like an odometer, least significant property last
+        let Some(digit) = (0..indices.len())
+            .rev()
+            .find(|digit| indices[*digit] + 1 < counts[*digit])
+        else {
+            break;
+        };
+        indices[digit] += 1;
+        indices[digit + 1..].fill(0);
+    }
+
+    Ok(result)
+}
+
+/// Get the block of kind _kind_ with all properties set to default values.
+///
+/// # Panics
+/// Panics if the kind is unknown or rejects its default state.
+fn default_state(kind: &str) -> Block {
+    let properties = Block::properties_of(kind).unwrap();
+    let values = properties
+        .iter()
+        .map(|property| property.ty.default_value())
+        .collect();
+    Block::from_properties(kind, values).expect("Default block state should be valid")
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+
+    use super::*;
+
+    #[test]
+    fn display_is_canonical() {
+        for (input, canonical) in [
+            ("air", "air"),
+            ("pusher[facing=east]", "pusher"),
+            (
+                "pusher[contents=sand,facing=north]",
+                "pusher[facing=north,contents=sand]",
+            ),
+            (
+                "pusher[contents=pusher[contents=stone],facing=up]",
+                "pusher[facing=up,contents=pusher[contents=stone]]",
+            ),
+            ("relay[delay=3,facing=east]", "relay[delay=3]"),
+            ("wire[power=3]", "wire[power=3]"),
+        ] {
+            let block = parse(input).unwrap();
+            assert_eq!(block.to_string(), canonical);
+            assert_eq!(parse(canonical).unwrap().to_string(), canonical);
+        }
+    }
+
+    #[test]
+    fn invalid_descriptions_are_rejected() {
+        let error = |input| parse(input).map(|_| ()).unwrap_err().error;
+
+        assert!(matches!(error("wood"), StateError::UnknownKind { .. }));
+        assert!(matches!(
+            error("pusher[foo=1]"),
+            StateError::UnknownProperty { .. }
+        ));
+        assert!(matches!(
+            error("pusher[facing=up,facing=down]"),
+            StateError::DuplicateProperty { .. }
+        ));
+        assert!(matches!(
+            error("pusher[facing=nort]"),
+            StateError::InvalidValue { .. }
+        ));
+        assert!(matches!(
+            error("wire[power=16]"),
+            StateError::InvalidValue { .. }
+        ));
+        assert!(matches!(
+            error("pusher[extended=true,contents=sand]"),
+            StateError::InvalidState { .. }
+        ));
+        for syntax in ["pusher[", "pusher[facing]", "stone ", "stone]", ""] {
+            assert!(
+                matches!(error(syntax), StateError::Syntax { .. }),
+                "{syntax:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn nesting_is_limited() {
+        let nested = |depth| {
+            let open = "pusher[contents=".repeat(depth);
+            format!("{open}sand{}", "]".repeat(depth))
+        };
+
+        assert!(parse(&nested(MAX_NESTING_DEPTH)).is_ok());
+        assert!(parse(&nested(MAX_NESTING_DEPTH + 1)).is_err());
+        assert!(parse(&nested(100_000)).is_err());
+    }
+
+    #[test]
+    fn all_states_are_distinct_and_round_trip() {
+        for kind in KindId::all() {
+            let states = all_states(kind.name()).unwrap();
+            assert!(!states.is_empty(), "{kind}");
+
+            let mut descriptions = HashSet::new();
+            for block in &states {
+                let description = block.to_string();
+                assert_eq!(parse(&description).unwrap().to_string(), description);
+
+                let nested = block.serialize_nested();
+                let deserialized = Block::deserialize(&nested.kind, &nested.data).unwrap();
+                assert_eq!(deserialized.to_string(), description);
+
+                assert!(descriptions.insert(description), "{block} is listed twice");
+            }
+        }
+
+        assert!(matches!(
+            all_states("wood"),
+            Err(StateError::UnknownKind { .. })
+        ));
+    }
+}
@@ -322,7 +322,7 @@ where
 ///
 /// # Example
 ///
-/// ```
+/// ```ignore
 /// let y = crash::with_context(("Value of x", || x), || flaky_function(x));
 /// ```
 pub fn with_context<V, S, F, R>(ctxt: (&'static str, S), action: F) -> R
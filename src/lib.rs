@@ -0,0 +1,13 @@
+//! Trapiron game library shared by the game binary and headless tools.
+//!
+//! [`world`] and [`logic`] can be used without a display; see `src/bin` for headless tools. The
+//! game client and GUI are only built with the `gui` feature, which is enabled by default.
+
+#[cfg(feature = "gui")]
+pub mod client;
+pub mod content;
+pub mod crash;
+#[cfg(feature = "gui")]
+pub mod gui;
+pub mod logic;
+pub mod world;
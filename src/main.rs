@@ -1,11 +1,6 @@
 use std::rc::Rc;
 
-pub mod client;
-pub mod content;
-pub mod crash;
-pub mod gui;
-pub mod logic;
-pub mod world;
+use trapiron::{client, crash, gui};
 
 struct MyApplication {
     game: client::Game,
@@ -14,7 +9,7 @@ struct MyApplication {
 impl MyApplication {
     fn new(gui: &mut gui::Gui) -> Self {
         println!("Loading resources");
-        let resources = Rc::new(content::Resources::new(gui));
+        let resources = Rc::new(client::Resources::new(gui));
 
         println!("Starting game");
         Self {
@@ -2,11 +2,13 @@
 //!
 //! In Trapiron, whenever there is a player character, a [World] is available; each block-based
 //! puzzles is stored in a [Level]. Both World and Level are only concerned with current game state,
-//! replays and resets are implemented separately.
+//! except that levels remember their initial blocks so that they can be [reset](Level::reset)
+//! cheaply. Replays are implemented separately.
 //!
 //! Changes in worlds and levels occur only in reaction to [_events_](Event), such as the player
 //! activating a button or a tick occurring. Events are serializable and reactions are
-//! deterministic, enabling a system of verifiable replays.
+//! deterministic, enabling a system of verifiable replays. To keep reactions bit-identical across
+//! platforms, simulation code must use [`math`] instead of transcendental functions from `std`.
 //!
 //! Non-tick events usually carry a change in player intent, while ticks act the intent and its
 //! consequences out: a jump input enters simulation space as a non-tick event that only applies a
@@ -33,12 +35,25 @@
 
 pub mod array3;
 pub mod character;
+pub mod collision;
+pub mod format;
+pub mod hash;
+pub mod math;
+pub mod metadata;
+pub mod raycast;
+pub mod replay;
+pub mod respawn;
+pub mod run;
+pub mod savestate;
+pub mod signal;
+pub mod transform;
+pub mod update;
 pub mod vec_iter;
 
-use std::time::Duration;
+use std::{sync::Arc, time::Duration};
 
 use crate::{
-    content::{self, Resources, block::Block},
+    content::block::{Block, Sand, Stone},
     logic::Logic,
     world::vec_iter::VecIterators,
 };
@@ -64,9 +79,112 @@ pub type Mat4 = glam::f32::Mat4;
 /// A Float 3x4 matrix vector (equivalent to mat4x3 in GLSL) for world state.
 pub type Affine3 = glam::f32::Affine3A;
 
+/// A Float quaternion for rotations in world state.
+pub type Quat = glam::f32::Quat;
+
 /// An unsigned integer 3D vector for world state.
 pub type UVec3 = glam::u32::UVec3;
 
+/// A signed integer 3D vector for world state.
+pub type IVec3 = glam::i32::IVec3;
+
+/// One of the six directions along the axes of a block grid.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Direction {
+    /// Towards positive X.
+    East,
+    /// Towards positive Y.
+    North,
+    /// Towards negative X.
+    West,
+    /// Towards negative Y.
+    South,
+    /// Towards positive Z.
+    Up,
+    /// Towards negative Z.
+    Down,
+}
+
+impl Direction {
+    /// All directions, in the order of their indices.
+    pub const ALL: [Direction; 6] = [
+        Self::East,
+        Self::North,
+        Self::West,
+        Self::South,
+        Self::Up,
+        Self::Down,
+    ];
+
+    /// Lowercase names of all directions, in the order of their indices.
+    pub const NAMES: [&'static str; 6] = ["east", "north", "west", "south", "up", "down"];
+
+    /// Get the index of this direction in [`Self::ALL`].
+    pub fn index(self) -> usize {
+        self as usize
+    }
+
+    /// Get the lowercase name of this direction, e.g. `"east"`.
+    pub fn name(self) -> &'static str {
+        Self::NAMES[self.index()]
+    }
+
+    /// Find a direction by its lowercase name.
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::NAMES
+            .iter()
+            .position(|n| *n == name)
+            .map(|index| Self::ALL[index])
+    }
+
+    /// Get the direction parallel to _axis_ (0 for X, 1 for Y and 2 for Z) that points towards
+    /// positive coordinates if _positive_ is set, or towards negative coordinates otherwise.
+    pub fn along(axis: usize, positive: bool) -> Self {
+        match (axis, positive) {
+            (0, true) => Self::East,
+            (0, false) => Self::West,
+            (1, true) => Self::North,
+            (1, false) => Self::South,
+            (2, true) => Self::Up,
+            (2, false) => Self::Down,
+            _ => panic!("axis {axis} does not exist"),
+        }
+    }
+
+    /// Get the unit grid offset in this direction.
+    pub fn offset(self) -> IVec3 {
+        match self {
+            Self::East => IVec3::X,
+            Self::North => IVec3::Y,
+            Self::West => IVec3::NEG_X,
+            Self::South => IVec3::NEG_Y,
+            Self::Up => IVec3::Z,
+            Self::Down => IVec3::NEG_Z,
+        }
+    }
+
+    /// Get the index of the axis this direction is parallel to: 0 for X, 1 for Y and 2 for Z.
+    pub fn axis(self) -> usize {
+        match self {
+            Self::East | Self::West => 0,
+            Self::North | Self::South => 1,
+            Self::Up | Self::Down => 2,
+        }
+    }
+
+    /// Get the opposite direction.
+    pub fn opposite(self) -> Self {
+        match self {
+            Self::East => Self::West,
+            Self::North => Self::South,
+            Self::West => Self::East,
+            Self::South => Self::North,
+            Self::Up => Self::Down,
+            Self::Down => Self::Up,
+        }
+    }
+}
+
 /// Euclidean angles yaw and pitch.
 #[derive(Debug, Clone, Copy, Default)]
 pub struct YawPitch {
@@ -78,6 +196,14 @@ pub struct YawPitch {
     pub pitch: Float,
 }
 
+impl YawPitch {
+    /// Get the unit vector that points in this direction in the world coordinate frame.
+    pub fn direction(&self) -> Vec3 {
+        let (sin, cos) = math::sin_cos(self.pitch);
+        math::rotate_z(Vec3::new(cos, 0.0, sin), -self.yaw)
+    }
+}
+
 /// A recorded change that can be applied to a [World].
 #[derive(Debug, Clone)]
 pub enum Event {
@@ -86,23 +212,49 @@ pub enum Event {
 
     /// A presentation tick has occurred.
     PresentationTick {
-        /// Step in simulation time that this tick corresponds to.
+        /// Step in simulation time that this tick corresponds to. At most
+        /// [`MAX_PRESENTATION_TICK_DURATION`].
         duration: Duration,
     },
 
     /// Change movement controls of player character.
     MovePlayerCharacter {
-        /// New movement controls for the player character.
-        ///
-        /// todo: replace with vec2 + jump input when collisions are added
-        direction: Vec3,
+        /// New horizontal movement controls for the player character relative to its yaw: X is
+        /// forward and Y is left. Length should not exceed 1.
+        direction: Vec2,
     },
 
+    /// Make the player character jump if it stands on a surface.
+    JumpPlayerCharacter,
+
     /// Change look angle of player character.
     SetPlayerCharacterRotation {
         /// New rotation of player character in the world coordinate frame.
         rotation: YawPitch,
     },
+
+    /// Move the player character back to its last [checkpoint](respawn::Checkpoint), resetting its
+    /// velocity and rotation, and revive it if it is dead.
+    RespawnPlayerCharacter,
+
+    /// Start over: [reset](Level::reset) all levels, put the player character back at its spawn
+    /// point and discard checkpoints and the current [run](run::Run).
+    Restart,
+
+    /// Make the player character use the first block in its line of sight, e.g. flip a lever or
+    /// press a button, if the block is within [reach](character::Character::REACH).
+    UseBlock {
+        /// Direction of the line of sight from the [eyes](character::Character::eye) of the player
+        /// character in the world coordinate frame. Need not be normalized.
+        direction: Vec3,
+    },
+
+    /// Replace the entire state of the world with a [savestate](savestate::Savestate) and mark
+    /// the run as a [practice run](run::Run::practice).
+    LoadSavestate {
+        /// The state to restore.
+        savestate: Arc<savestate::Savestate>,
+    },
 }
 
 /// Expected number of logic ticks per simulation second.
@@ -113,49 +265,124 @@ pub fn target_tick_duration() -> Duration {
     std::time::Duration::from_secs(1) / crate::world::TARGET_TPS
 }
 
+/// Longest step in simulation time a single presentation tick may correspond to.
+///
+/// Longer pauses between frames, e.g. while the window is being dragged, are shortened to this
+/// duration so that the simulation does not jump.
+pub const MAX_PRESENTATION_TICK_DURATION: Duration = Duration::from_secs(1);
+
 /// The state of a level: a portion of a [world](World) with a mutable block grid that can be
 /// attempted.
+#[derive(Clone)]
 pub struct Level {
     pub blocks: array3::Array3<Block>,
 
     /// Location of the origin of the level in world coordinates.
     pub position: Vec3,
 
-    /// Rotation of the level coordinate system around Z axis. Applied after position.
-    pub yaw: Float,
+    /// Rotation of the level coordinate system relative to the world as a unit quaternion. Applied
+    /// before position, see [`transform`].
+    pub rotation: Quat,
+
+    /// Block updates waiting for their logic tick.
+    pub updates: update::Queue,
+
+    /// Name, spawn point, target times and other information about the level.
+    pub metadata: metadata::Metadata,
+
+    /// The blocks that [`Self::reset`] restores.
+    initial: Arc<array3::Array3<Block>>,
 }
 
 impl Level {
-    /// tmp
-    pub fn new(rsrc: &Resources) -> Self {
-        let block = |name: &str| {
-            // Wow, this must be the filthiest code I ever wrote
-            let ser = (name.chars().last().unwrap() as u32) - ('0' as u32);
-            let serialized = content::block::Serialized(ser);
-            let len = name.len();
-            rsrc.blocks
-                .get(&name[..len - 2])
-                .unwrap()
-                .instantiate(&serialized)
-        };
+    /// Create a level with _blocks_ at _position_ rotated by _rotation_, with no pending block
+    /// updates and empty metadata.
+    ///
+    /// _blocks_ also become the initial state of the level, see [`Self::reset`]. _rotation_ must be
+    /// a unit quaternion, see [`math::normalize`].
+    pub fn from_blocks(blocks: array3::Array3<Block>, position: Vec3, rotation: Quat) -> Self {
+        Self {
+            initial: Arc::new(blocks.clone()),
+            blocks,
+            position,
+            rotation,
+            updates: update::Queue::new(),
+            metadata: metadata::Metadata::default(),
+        }
+    }
 
-        let mut result = Self {
-            blocks: array3::Array3::default(UVec3::new(10, 10, 10)),
-            position: Vec3::new(0., 5., 0.),
-            yaw: 0.1,
-        };
+    /// Create the hardcoded test level, see [`replay::TEST_LEVEL`]: a tilted stone floor with a
+    /// ring of sand around its center.
+    pub fn test() -> Self {
+        let mut result = Self::from_blocks(
+            array3::Array3::default(UVec3::new(10, 10, 10)),
+            Vec3::new(0., 5., 0.),
+            math::rotation_z(-0.1),
+        );
 
         for col in UVec3::ZERO.iter_box(&result.blocks.shape().with_z(1)) {
-            result.blocks[col.with_z(0)] = block("stone:0");
+            result.blocks[col.with_z(0)] = Block::Stone(Stone);
             let dx = 5i32 - (col.x as i32);
             let dy = 5i32 - (col.y as i32);
             if dx * dx + dy * dy > 15 {
-                result.blocks[col.with_z(1)] = block("sand:0");
+                result.blocks[col.with_z(1)] = Block::Sand(Sand { falling: false });
             }
         }
 
+        result.metadata.name = "Test level".to_string();
+        result.save_initial_state();
         result
     }
+
+    /// Make the current blocks the initial state of this level, which [`Self::reset`] restores.
+    ///
+    /// Use this after editing the blocks of a level directly rather than through
+    /// [`Self::set_block`] in gameplay.
+    pub fn save_initial_state(&mut self) {
+        self.initial = Arc::new(self.blocks.clone());
+    }
+
+    /// Get the blocks of the initial state of this level.
+    pub fn initial_blocks(&self) -> &array3::Array3<Block> {
+        &self.initial
+    }
+
+    /// Restore the initial blocks of this level and replace all pending block updates with the
+    /// ones a freshly loaded level receives, see [`World::from_levels`].
+    ///
+    /// This is cheaper than loading the level again: the block grid is overwritten in place.
+    pub fn reset(&mut self) {
+        self.blocks.clone_from(&self.initial);
+        self.updates = update::Queue::new();
+        self.queue_initial_updates();
+    }
+
+    /// Queue a [placement](update::Cause::Placed) update of every sand block, so that sand that
+    /// nothing supports in the initial state starts falling in the first logic tick.
+    fn queue_initial_updates(&mut self) {
+        let sand: Vec<_> = self
+            .blocks
+            .pos_iter()
+            .filter(|(_, block)| matches!(block, Block::Sand(_)))
+            .map(|(pos, _)| pos.as_ivec3())
+            .collect();
+
+        for pos in sand {
+            let update = update::Update {
+                pos,
+                cause: update::Cause::Placed,
+            };
+            self.updates.push(update, 1);
+        }
+    }
+
+    /// Get the block at _pos_ in level coordinates, or `None` if _pos_ is outside the level.
+    pub fn block(&self, pos: IVec3) -> Option<&Block> {
+        if pos.cmplt(IVec3::ZERO).any() {
+            return None;
+        }
+        self.blocks.get(pos.as_uvec3())
+    }
 }
 
 /// tmp: camera should be bound to player OR noclip. Maybe even cutscenes.
@@ -177,27 +404,104 @@ pub struct Camera {
 ///
 /// Updated by discrete events, including logic and presentation ticks. See module description for
 /// more details.
+#[derive(Clone)]
 pub struct World {
     pub levels: Vec<Level>,
     pub player: character::Character,
+
+    /// The timed attempt of the player to reach the finish.
+    pub run: run::Run,
+
+    /// Where the player character respawns.
+    pub checkpoint: respawn::Checkpoint,
+
+    /// Where the player character starts, and the first checkpoint after a restart.
+    pub spawn: respawn::Checkpoint,
 }
 
 impl World {
-    /// tmp
-    pub fn new(rsrc: &Resources) -> Self {
+    /// Create a world consisting of _levels_ with the player character in its initial state.
+    ///
+    /// The character starts at the spawn point of the levels, see [`metadata`]. Sand blocks of the
+    /// levels receive an update in the first logic tick, so that unsupported sand starts falling.
+    pub fn from_levels(mut levels: Vec<Level>) -> Self {
+        for level in &mut levels {
+            level.queue_initial_updates();
+        }
+        let spawn = Self::find_spawn(&levels);
+
+        let mut player = character::Character::new();
+        player.position = spawn.position;
+        player.rotation = spawn.rotation;
+
         Self {
-            levels: vec![Level::new(rsrc)],
-            player: character::Character::new(),
+            levels,
+            player,
+            run: run::Run::new(),
+            checkpoint: spawn,
+            spawn,
         }
     }
 
     /// Process an event related to a logic tick.
     pub fn process(&mut self, event: Event, _logic: &Logic) {
-        match event {
-            Event::LogicTick => {}
+        match &event {
+            Event::LogicTick => {
+                for level in &mut self.levels {
+                    // Blocks are centered on integer coordinates
+                    let feet = level.to_local(self.player.position);
+                    let occupied = (feet + 0.5).floor().as_ivec3();
+                    level.logic_tick(&[occupied]);
+                }
+            }
+            Event::UseBlock { direction } => self.use_block(*direction),
+            Event::RespawnPlayerCharacter => self.respawn(),
+            Event::Restart => self.restart(),
+            Event::LoadSavestate { savestate } => self.load_savestate(savestate),
             _ => {}
         }
 
-        self.player.process(&event);
+        let previous = self.player.position;
+        self.player.process(&event, &self.levels);
+        if let Event::PresentationTick { .. } = event {
+            self.check_hazards(previous);
+        }
+        self.run
+            .process(&event, previous, &self.levels, &self.player);
+    }
+
+    /// Reset all levels and the player character to their initial state and start a new run.
+    ///
+    /// Movement controls are kept, and a new run starts immediately if the player is moving. The
+    /// new run remains a [practice run](run::Run::practice) if the current one is.
+    fn restart(&mut self) {
+        for level in &mut self.levels {
+            level.reset();
+        }
+
+        self.checkpoint = self.spawn;
+        self.respawn();
+
+        self.run = run::Run {
+            practice: self.run.practice,
+            ..run::Run::new()
+        };
+        if self.player.control() != Vec2::ZERO {
+            self.run.start();
+        }
+    }
+
+    /// Trigger the first block that the player character sees along _direction_ within reach.
+    ///
+    /// The block reacts in the next logic tick, see [`Level::trigger`].
+    fn use_block(&mut self, direction: Vec3) {
+        let Some(direction) = direction.try_normalize() else {
+            return;
+        };
+        let eye = self.player.eye();
+        let reach = character::Character::REACH;
+        if let Some((level, hit)) = self.raycast(eye, direction, reach, raycast::Target::Cell) {
+            self.levels[level].trigger(hit.pos);
+        }
     }
 }
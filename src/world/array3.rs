@@ -6,6 +6,7 @@ use crate::world::{
 };
 
 /// An optimized dynamically allocated 3D array of _T_.
+#[derive(Clone)]
 pub struct Array3<T> {
     /// The objects stored in this array.
     ///
@@ -84,6 +85,20 @@ impl<T> Array3<T> {
 
         Self { data, shape }
     }
+
+    /// Create a new array with given shape from its elements.
+    ///
+    /// Elements of _data_ must be ordered by increasing Z, then by increasing Y, then by increasing
+    /// X, matching [`Self::pos_iter`].
+    ///
+    /// Returns `None` if the length of _data_ does not match _shape_.
+    pub fn from_vec(shape: UVec3, data: Vec<T>) -> Option<Self> {
+        if Self::checked_len_from_shape(shape)? == data.len() {
+            Some(Self { data, shape })
+        } else {
+            None
+        }
+    }
 }
 
 impl<T: Clone> Array3<T> {
@@ -113,6 +128,20 @@ impl<T> Array3<T> {
     pub fn shape(&self) -> UVec3 {
         self.shape
     }
+
+    /// Get a reference to the element at _pos_, or `None` if _pos_ is out of bounds.
+    pub fn get(&self, pos: UVec3) -> Option<&T> {
+        let index = self.try_pos_to_index(pos)?;
+        // SAFETY: try_pos_to_index only returns valid indices.
+        unsafe { Some(self.data.get_unchecked(index)) }
+    }
+
+    /// Get a mutable reference to the element at _pos_, or `None` if _pos_ is out of bounds.
+    pub fn get_mut(&mut self, pos: UVec3) -> Option<&mut T> {
+        let index = self.try_pos_to_index(pos)?;
+        // SAFETY: try_pos_to_index only returns valid indices.
+        unsafe { Some(self.data.get_unchecked_mut(index)) }
+    }
 }
 
 impl<T> std::ops::Index<UVec3> for Array3<T> {
@@ -1,8 +1,12 @@
 //! Character state and related concepts.
 
-use super::*;
+use super::{
+    hash::{StateHash, StateHasher},
+    *,
+};
 
 /// State of the player character relevant to the game logic.
+#[derive(Clone)]
 pub struct Character {
     /// Position of character (center of feet) in world coordinate frame.
     ///
@@ -22,21 +26,45 @@ pub struct Character {
     /// differ from this value due to non-input frames.
     pub rotation: YawPitch,
 
-    /// Direction the character wishes to move in.
+    /// Whether the character stands on a surface and can jump.
     ///
-    /// todo: replace with vec2 + jump input when collisions are added
+    /// This property is updated once per presentation tick.
+    pub grounded: bool,
+
+    /// The [friction](crate::content::block::Physics::friction) of the surface the character
+    /// stands on, which scales how fast it can change its speed. Only meaningful while
+    /// [grounded](Self::grounded).
     ///
     /// This property is updated once per presentation tick.
-    control: Vec3,
+    pub friction: Float,
+
+    /// Whether the character is alive. Dead characters do not move or jump until they
+    /// [respawn](crate::world::respawn).
+    pub alive: bool,
+
+    /// Direction the character wishes to move in, relative to its yaw; X is forward and Y is left.
+    control: Vec2,
 }
 
 impl Character {
+    /// The shape of the character's body relative to its [position](Self::position).
+    pub const BODY: collision::Aabb = collision::Aabb {
+        min: Vec3::new(-0.3, -0.3, 0.0),
+        max: Vec3::new(0.3, 0.3, 1.8),
+    };
+
+    /// Maximum distance from the [eyes](Self::eye) at which the character can use blocks.
+    pub const REACH: Float = 4.0;
+
     /// tmp
     pub fn new() -> Self {
         Self {
             position: Default::default(),
             velocity: Default::default(),
             rotation: Default::default(),
+            grounded: false,
+            friction: 0.0,
+            alive: true,
             control: Default::default(),
         }
     }
@@ -49,24 +77,67 @@ impl Character {
         self.position + EYE_POS
     }
 
+    /// Get the direction the character wishes to move in, relative to its yaw; X is forward and Y
+    /// is left.
+    pub fn control(&self) -> Vec2 {
+        self.control
+    }
+
+    /// Set the movement controls directly, e.g. to restore a
+    /// [savestate](crate::world::savestate). In gameplay, controls only change through
+    /// [`Event::MovePlayerCharacter`].
+    pub(crate) fn set_control(&mut self, control: Vec2) {
+        self.control = control;
+    }
+
     /// Handle an event and update self accordingly if necessary.
-    pub fn process(&mut self, event: &Event) {
+    ///
+    /// The character collides with solid blocks of _levels_.
+    pub fn process(&mut self, event: &Event, levels: &[Level]) {
         match event {
-            Event::PresentationTick { duration } => {
+            Event::PresentationTick { duration } if self.alive => {
                 let dt: Float = duration.as_secs_f32();
 
-                const CONTROL_ACCELERATION: Float = 50.0;
+                const GROUND_ACCELERATION: Float = 50.0;
+                const AIR_ACCELERATION: Float = 10.0;
                 const CONTROL_SPEED: Float = 5.0;
+                const GRAVITY: Float = 32.0;
+                const CLIMB_SPEED: Float = 3.0;
+
+                /// Base 2 logarithm of the fraction of horizontal velocity retained after one
+                /// second.
+                const DRAG_LOG2: Float = -2.0;
+
+                let acceleration = if self.grounded {
+                    GROUND_ACCELERATION * self.friction
+                } else {
+                    AIR_ACCELERATION
+                };
 
                 let target =
-                    Mat3::from_rotation_z(-self.rotation.yaw) * self.control * CONTROL_SPEED;
+                    math::rotate_z(self.control.extend(0.0), -self.rotation.yaw) * CONTROL_SPEED;
+                let dv = (target - self.velocity).truncate();
+                let dv = dv.clamp_length_max(acceleration * dt);
+                self.velocity += dv.extend(-GRAVITY * dt);
+
+                let contacts = collision::move_body(
+                    levels,
+                    &Self::BODY,
+                    &mut self.position,
+                    &mut self.velocity,
+                    dt,
+                );
+                self.grounded = contacts.floor;
+                self.friction = contacts.friction;
 
-                let dv = target - self.velocity;
-                let dv = dv.clamp_length_max(CONTROL_ACCELERATION * dt);
-                self.velocity += dv;
+                // Characters that walk into a climbable wall climb up along it
+                if contacts.climbable && self.control.x > 0.0 {
+                    self.velocity.z = self.velocity.z.max(CLIMB_SPEED);
+                }
 
-                self.position += self.velocity * dt;
-                self.velocity *= (0.25 as Float).powf(dt); // TODO powf is not deterministic
+                let drag = math::exp2(DRAG_LOG2 * dt);
+                self.velocity.x *= drag;
+                self.velocity.y *= drag;
             }
             Event::SetPlayerCharacterRotation { rotation } => {
                 self.rotation = *rotation;
@@ -74,7 +145,148 @@ impl Character {
             Event::MovePlayerCharacter { direction } => {
                 self.control = *direction;
             }
+            Event::JumpPlayerCharacter => {
+                const JUMP_SPEED: Float = 9.0;
+
+                if self.grounded && self.alive {
+                    self.velocity.z = JUMP_SPEED;
+                    self.grounded = false;
+                }
+            }
             _ => {}
         }
     }
 }
+
+impl StateHash for Character {
+    fn state_hash(&self, hasher: &mut StateHasher) {
+        self.position.state_hash(hasher);
+        self.velocity.state_hash(hasher);
+        self.rotation.state_hash(hasher);
+        hasher.write_u8(self.grounded as u8);
+        self.friction.state_hash(hasher);
+        hasher.write_u8(self.alive as u8);
+        self.control.state_hash(hasher);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+    use crate::content::block::{Block, Sand, Stone};
+
+    fn bits(v: Vec3) -> [u32; 3] {
+        v.to_array().map(Float::to_bits)
+    }
+
+    fn process_all(character: &mut Character, events: &[Event], levels: &[Level]) {
+        for event in events {
+            character.process(event, levels);
+        }
+    }
+
+    /// Drive a character over a stone floor through a fixed sequence of events and compare its
+    /// state bit-exactly with values pinned on a reference platform.
+    #[test]
+    fn fixed_event_sequence_is_pinned() {
+        let blocks = array3::Array3::fill(UVec3::new(16, 16, 1), &Block::Stone(Stone {}));
+        let levels = [Level::from_blocks(blocks, Vec3::ZERO, Quat::IDENTITY)];
+
+        let mut character = Character::new();
+        character.position = Vec3::new(4.0, 4.0, 2.0);
+
+        let tick = |micros| Event::PresentationTick {
+            duration: Duration::from_micros(micros),
+        };
+        let process = |character: &mut Character, events: Vec<Event>| {
+            process_all(character, &events, &levels);
+        };
+
+        // Fall onto the floor, turn, run and jump
+        process(&mut character, vec![tick(16_667); 40]);
+        process(
+            &mut character,
+            vec![
+                Event::SetPlayerCharacterRotation {
+                    rotation: YawPitch {
+                        yaw: 0.7,
+                        pitch: 0.0,
+                    },
+                },
+                Event::MovePlayerCharacter {
+                    direction: Vec2::new(1.0, 0.0),
+                },
+            ],
+        );
+        process(&mut character, vec![tick(16_667); 30]);
+        process(&mut character, vec![Event::JumpPlayerCharacter]);
+        process(&mut character, vec![tick(16_667); 20]);
+
+        assert_eq!(
+            bits(character.position),
+            [0x40e0_9de5, 0x3fba_7bf4, 0x3fd1_1098]
+        );
+        assert_eq!(
+            bits(character.velocity),
+            [0x406f_28c9, 0xc049_70ec, 0xbfd5_5c48]
+        );
+        assert!(!character.grounded);
+
+        // Strafe at a different frame rate, land and stop
+        process(
+            &mut character,
+            vec![Event::MovePlayerCharacter {
+                direction: Vec2::new(0.0, -1.0),
+            }],
+        );
+        process(&mut character, vec![tick(7_000); 25]);
+        process(
+            &mut character,
+            vec![Event::MovePlayerCharacter {
+                direction: Vec2::ZERO,
+            }],
+        );
+        process(&mut character, vec![tick(16_667); 30]);
+
+        assert_eq!(
+            bits(character.position),
+            [0x40f1_1e29, 0x3f4b_2e00, 0x3f00_0000]
+        );
+        assert_eq!(bits(character.velocity), [0; 3]);
+        assert!(character.grounded);
+    }
+
+    /// Run forward for a few ticks on a floor made of _floor_ and return the distance covered.
+    fn run_on(floor: Block) -> Float {
+        let blocks = array3::Array3::fill(UVec3::new(16, 16, 1), &floor);
+        let levels = [Level::from_blocks(blocks, Vec3::ZERO, Quat::IDENTITY)];
+
+        let mut character = Character::new();
+        character.position = Vec3::new(4.0, 8.0, 0.5);
+        let tick = Event::PresentationTick {
+            duration: Duration::from_millis(10),
+        };
+        process_all(&mut character, &[tick.clone(), tick.clone()], &levels);
+        assert!(character.grounded);
+
+        let start = character.position;
+        character.process(
+            &Event::MovePlayerCharacter {
+                direction: Vec2::new(1.0, 0.0),
+            },
+            &levels,
+        );
+        process_all(&mut character, &vec![tick; 10], &levels);
+        character.position.distance(start)
+    }
+
+    #[test]
+    fn floor_friction_limits_acceleration() {
+        let stone = run_on(Block::Stone(Stone));
+        let sand = run_on(Block::Sand(Sand { falling: false }));
+        assert!(sand > 0.0);
+        assert!(sand < stone, "{sand} on sand, {stone} on stone");
+    }
+}
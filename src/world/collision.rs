@@ -0,0 +1,431 @@
+//! Collision of moving bodies with the solid blocks of levels.
+//!
+//! Bodies are boxes aligned with the world axes, and blocks are made of boxes according to their
+//! [physics](crate::content::block::Physics::shape). Since every level has its own coordinate
+//! frame, collisions with a level are resolved in its frame, where the body is approximated by a
+//! [box aligned with the grid](Level::body_to_local). Surfaces of blocks that face the [up
+//! direction](Level::up) of their level are floors, so bodies can stand in rotated levels.
+//!
+//! Movement is split into substeps no longer than [`MAX_SUBSTEP`], so that fast bodies cannot pass
+//! through blocks. Within a substep, movement is resolved along each axis separately: a body that
+//! hits a surface stops along the axis perpendicular to it but keeps moving along the others,
+//! sliding along the surface. Bodies bounce off [bouncy](Physics::bounciness) blocks instead of
+//! stopping.
+//!
+//! Blocks that react to bodies rather than stop them, such as finish blocks, find out which bodies
+//! touch them with [`first_touch`].
+
+use crate::{
+    content::block::{Block, Physics},
+    world::{Direction, Float, IVec3, Level, Vec3, vec_iter::VecIterators},
+};
+
+/// An axis-aligned box relative to some origin.
+#[derive(Debug, Clone, Copy)]
+pub struct Aabb {
+    /// The corner with the smallest coordinates.
+    pub min: Vec3,
+
+    /// The corner with the greatest coordinates.
+    pub max: Vec3,
+}
+
+impl Aabb {
+    /// Find where a ray from _origin_ along _direction_ first hits this box, and through which
+    /// face.
+    ///
+    /// Returns the distance along the ray in multiples of the length of _direction_, and the face,
+    /// or `None` for the face if _origin_ lies inside the box. Touching the surface of the box
+    /// counts as a hit.
+    pub fn intersect_ray(
+        &self,
+        origin: Vec3,
+        direction: Vec3,
+    ) -> Option<(Float, Option<Direction>)> {
+        let mut enter = (Float::NEG_INFINITY, None);
+        let mut exit = Float::INFINITY;
+
+        for axis in 0..3 {
+            if direction[axis] == 0.0 {
+                if origin[axis] < self.min[axis] || origin[axis] > self.max[axis] {
+                    return None;
+                }
+                continue;
+            }
+
+            let to_min = (self.min[axis] - origin[axis]) / direction[axis];
+            let to_max = (self.max[axis] - origin[axis]) / direction[axis];
+            let (near, far) = if direction[axis] > 0.0 {
+                (to_min, to_max)
+            } else {
+                (to_max, to_min)
+            };
+
+            if near > enter.0 {
+                enter = (near, Some(Direction::along(axis, direction[axis] < 0.0)));
+            }
+            exit = exit.min(far);
+        }
+
+        if exit < enter.0.max(0.0) {
+            return None;
+        }
+        if enter.0 < 0.0 {
+            return Some((0.0, None));
+        }
+        Some(enter)
+    }
+}
+
+/// Surfaces that a body hit while [moving](move_body).
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Contacts {
+    /// The body hit a surface below it, i.e. landed on or stands on something.
+    pub floor: bool,
+
+    /// The body hit a surface above it.
+    pub ceiling: bool,
+
+    /// The body hit a vertical surface.
+    pub wall: bool,
+
+    /// The [friction](Physics::friction) of the floor the body hit, or the greatest one if it hit
+    /// several. Zero if the body hit no floor.
+    pub friction: Float,
+
+    /// The body hit a vertical surface of a [climbable](Physics::climbable) block.
+    pub climbable: bool,
+}
+
+impl Contacts {
+    /// Record that the body hit a surface of a block with _physics_ that faces _normal_, in a level
+    /// whose up direction is _up_.
+    fn record(&mut self, normal: Direction, up: Direction, physics: &Physics) {
+        if normal == up {
+            self.floor = true;
+            self.friction = self.friction.max(physics.friction);
+        } else if normal == up.opposite() {
+            self.ceiling = true;
+        } else {
+            self.wall = true;
+            self.climbable |= physics.climbable;
+        }
+    }
+}
+
+/// Maximum distance a body may move in a single substep.
+///
+/// Must be smaller than a block and than any body so that no body can pass through a block.
+pub const MAX_SUBSTEP: Float = 0.25;
+
+/// Maximum number of substeps in a single movement. Movements that would require more substeps
+/// are truncated to `MAX_SUBSTEPS` substeps of [`MAX_SUBSTEP`].
+const MAX_SUBSTEPS: u32 = 256;
+
+/// Depth up to which a body may intersect a block and still be considered touching it rather than
+/// stuck in it. Absorbs rounding errors of level transforms.
+const SKIN: Float = 1.0 / 1024.0;
+
+/// Move a body with shape _body_ relative to _position_ along _velocity_ for _dt_ seconds,
+/// stopping at solid blocks of _levels_.
+///
+/// _position_ and _velocity_ are in world coordinates. When the body hits a surface, the component
+/// of _velocity_ perpendicular to the surface [rebounds](rebound) off it.
+pub fn move_body(
+    levels: &[Level],
+    body: &Aabb,
+    position: &mut Vec3,
+    velocity: &mut Vec3,
+    dt: Float,
+) -> Contacts {
+    let mut contacts = Contacts::default();
+
+    let speed = velocity.length();
+    let substeps = ((speed * dt / MAX_SUBSTEP).ceil() as u32).clamp(1, MAX_SUBSTEPS);
+    let mut substep_dt = dt / substeps as Float;
+    if substeps == MAX_SUBSTEPS {
+        // Truncate the movement rather than let substeps grow long enough to skip blocks
+        substep_dt = substep_dt.min(MAX_SUBSTEP / speed);
+    }
+
+    for _ in 0..substeps {
+        let mut displacement = *velocity * substep_dt;
+        for level in levels {
+            clip_to_level(
+                level,
+                body,
+                *position,
+                &mut displacement,
+                velocity,
+                &mut contacts,
+            );
+        }
+        *position += displacement;
+    }
+
+    contacts
+}
+
+/// Reduce _displacement_ of a body with shape _body_ at _position_ so that it does not enter solid
+/// blocks of _level_, [rebound](rebound) _velocity_ along blocked axes and record hit surfaces in
+/// _contacts_.
+///
+/// All vectors are in world coordinates. They are only modified if the body hits a block.
+fn clip_to_level(
+    level: &Level,
+    body: &Aabb,
+    position: Vec3,
+    displacement: &mut Vec3,
+    velocity: &mut Vec3,
+    contacts: &mut Contacts,
+) {
+    let body = level.body_to_local(body);
+    let up = level.up();
+    let mut local_position = level.to_local(position);
+    let wanted = level.vector_to_local(*displacement);
+    let mut allowed = wanted;
+
+    // Resolve vertical movement first so that bodies slide along floors
+    let mut obstacles = [None; 3];
+    let others = (0..3).filter(|axis| *axis != up.axis());
+    for axis in std::iter::once(up.axis()).chain(others) {
+        (allowed[axis], obstacles[axis]) =
+            clip_axis(level, &body, local_position, axis, wanted[axis]);
+        local_position[axis] += allowed[axis];
+    }
+
+    if allowed != wanted {
+        *displacement = level.vector_to_world(allowed);
+
+        let mut local_velocity = level.vector_to_local(*velocity);
+        for axis in 0..3 {
+            if let Some(physics) = &obstacles[axis] {
+                local_velocity[axis] = rebound(local_velocity[axis], physics);
+
+                // The surface that stopped the body faces against the movement
+                let normal = Direction::along(axis, wanted[axis] < 0.0);
+                contacts.record(normal, up, physics);
+            }
+        }
+        *velocity = level.vector_to_world(local_velocity);
+    }
+}
+
+/// Compute the velocity of a body along an axis after it hit a block with _physics_ while moving
+/// at _velocity_ along that axis.
+///
+/// The body bounces back with its speed scaled by the [bounciness](Physics::bounciness) of the
+/// block, or stops if the block does not bounce.
+fn rebound(velocity: Float, physics: &Physics) -> Float {
+    if physics.bounciness > 0.0 {
+        -velocity * physics.bounciness
+    } else {
+        0.0
+    }
+}
+
+/// Compute how far a body with shape _body_ at _position_ can move by _delta_ along _axis_ before
+/// hitting a solid block of _level_, and the physics of the block it hits, if any.
+///
+/// All coordinates are in the frame of _level_. Parts of blocks that the body is already stuck in
+/// are ignored so that it can always move out.
+fn clip_axis(
+    level: &Level,
+    body: &Aabb,
+    position: Vec3,
+    axis: usize,
+    delta: Float,
+) -> (Float, Option<Physics>) {
+    if delta == 0.0 {
+        return (0.0, None);
+    }
+
+    let start_min = position + body.min;
+    let start_max = position + body.max;
+
+    let mut swept_min = start_min;
+    let mut swept_max = start_max;
+    if delta > 0.0 {
+        swept_max[axis] += delta;
+    } else {
+        swept_min[axis] += delta;
+    }
+
+    // Blocks at p lie within p - 0.5 to p + 0.5; find all blocks that the swept box may intersect
+    let first = (swept_min + 0.5).floor().as_ivec3();
+    let last = (swept_max + 0.5).ceil().as_ivec3();
+    let shape = level.blocks.shape().as_ivec3();
+    let begin = first.clamp(IVec3::ZERO, shape).as_uvec3();
+    let end = last.clamp(IVec3::ZERO, shape).as_uvec3();
+
+    let mut result = delta;
+    let mut obstacle = None;
+    for pos in begin.iter_box(&end) {
+        let physics = level.blocks[pos].physics();
+        for part in physics.shape {
+            let part_min = pos.as_vec3() + part.min;
+            let part_max = pos.as_vec3() + part.max;
+
+            // Whether the body overlaps the part along an axis by more than SKIN
+            let overlaps =
+                |a: usize| start_min[a] + SKIN < part_max[a] && start_max[a] - SKIN > part_min[a];
+
+            // Parts beside the body and parts it is stuck in are not obstacles
+            if overlaps(axis) || (0..3).any(|a| a != axis && !overlaps(a)) {
+                continue;
+            }
+
+            let limit = if delta > 0.0 && part_min[axis] >= start_max[axis] - SKIN {
+                (part_min[axis] - start_max[axis]).max(0.0)
+            } else if delta < 0.0 && part_max[axis] <= start_min[axis] + SKIN {
+                (part_max[axis] - start_min[axis]).min(0.0)
+            } else {
+                continue;
+            };
+            if limit.abs() < result.abs() {
+                result = limit;
+                obstacle = Some(physics);
+            }
+        }
+    }
+
+    (result, obstacle)
+}
+
+/// The moment a moving body first touched a block, see [`first_touch`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Touch {
+    /// Index of the level of the block in the slice of levels that was searched.
+    pub level: usize,
+
+    /// Position of the block in level coordinates.
+    pub pos: IVec3,
+
+    /// How far the body had moved when it touched the block, as a fraction of the way between
+    /// `0.0` and `1.0`.
+    pub fraction: Float,
+}
+
+/// Find the first block of _levels_ accepted by _filter_ that a body with shape _body_ touched
+/// while moving in a straight line from _from_ to _to_.
+///
+/// _from_ and _to_ are positions of the body in world coordinates. A block is touched when the
+/// body intersects or touches its cell, regardless of the collision shape of the block, so both
+/// blocks that bodies pass through and the surfaces of solid blocks can be touched. When several
+/// blocks are touched at the same moment, the first one in level and position order is returned.
+pub fn first_touch(
+    levels: &[Level],
+    body: &Aabb,
+    from: Vec3,
+    to: Vec3,
+    filter: impl Fn(&Block) -> bool,
+) -> Option<Touch> {
+    let mut result: Option<Touch> = None;
+
+    for (index, level) in levels.iter().enumerate() {
+        let body = level.body_to_local(body);
+        let from = level.to_local(from);
+        let to = level.to_local(to);
+
+        // Blocks at p lie within p - 0.5 to p + 0.5; find all blocks the body may have touched
+        let swept_min = from.min(to) + body.min - SKIN;
+        let swept_max = from.max(to) + body.max + SKIN;
+        let first = (swept_min + 0.5).ceil().as_ivec3() - 1;
+        let last = (swept_max + 0.5).floor().as_ivec3();
+        let shape = level.blocks.shape().as_ivec3();
+        let begin = first.clamp(IVec3::ZERO, shape).as_uvec3();
+        let end = (last + 1).clamp(IVec3::ZERO, shape).as_uvec3();
+
+        for pos in begin.iter_box(&end) {
+            if !filter(&level.blocks[pos]) {
+                continue;
+            }
+
+            // The body touches the cell while its position is within the cell grown by the body
+            let cell = pos.as_vec3();
+            let reach = Aabb {
+                min: cell - 0.5 - body.max - SKIN,
+                max: cell + 0.5 - body.min + SKIN,
+            };
+            let Some((fraction, _)) = reach.intersect_ray(from, to - from) else {
+                continue;
+            };
+            if fraction <= 1.0 && result.is_none_or(|best| fraction < best.fraction) {
+                result = Some(Touch {
+                    level: index,
+                    pos: pos.as_ivec3(),
+                    fraction,
+                });
+            }
+        }
+    }
+
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        content::block::Stone,
+        world::{Quat, UVec3, array3::Array3},
+    };
+
+    /// Fast bodies and long time steps must not carry a body past a wall one block thick. Moving
+    /// diagonally in one long step would resolve along X first and slip through the gap below the
+    /// wall.
+    #[test]
+    fn fast_body_does_not_pass_through_wall() {
+        let mut blocks = Array3::default(UVec3::new(16, 16, 3));
+        for y in 4..16 {
+            blocks[UVec3::new(8, y, 1)] = Block::Stone(Stone);
+        }
+        let levels = [Level::from_blocks(blocks, Vec3::ZERO, Quat::IDENTITY)];
+        let body = Aabb {
+            min: Vec3::splat(-0.25),
+            max: Vec3::splat(0.25),
+        };
+
+        for (speed, dt) in [(1.0e6, 1.0 / 60.0), (20.0, 1000.0)] {
+            let mut position = Vec3::new(2.0, 2.0, 1.0);
+            let mut velocity = Vec3::new(speed, speed, 0.0);
+
+            let contacts = move_body(&levels, &body, &mut position, &mut velocity, dt);
+
+            assert!(contacts.wall, "speed {speed}, dt {dt}");
+            assert_eq!(velocity.x, 0.0, "speed {speed}, dt {dt}");
+            assert!(position.x <= 7.25, "speed {speed}, dt {dt}: {position}");
+        }
+    }
+
+    #[test]
+    fn rebound_scales_speed_by_bounciness() {
+        let bouncy = Physics {
+            bounciness: 0.5,
+            ..Physics::SOLID
+        };
+        assert_eq!(rebound(-4.0, &bouncy), 2.0);
+        assert_eq!(rebound(-4.0, &Physics::SOLID).to_bits(), 0.0f32.to_bits());
+    }
+
+    #[test]
+    fn contacts_record_floor_friction_and_climbable_walls() {
+        let ladder = Physics {
+            friction: 0.5,
+            climbable: true,
+            ..Physics::SOLID
+        };
+        let up = Direction::Up;
+
+        let mut contacts = Contacts::default();
+        contacts.record(up, up, &ladder);
+        contacts.record(up, up, &Physics::SOLID);
+        assert!(contacts.floor);
+        assert_eq!(contacts.friction, 1.0);
+        assert!(!contacts.climbable);
+
+        contacts.record(Direction::East, up, &ladder);
+        assert!(contacts.wall);
+        assert!(contacts.climbable);
+    }
+}
@@ -0,0 +1,302 @@
+//! File formats for storing [levels](crate::world::Level) and related data.
+//!
+//! - [`binary`]: compact versioned binary format.
+//! - [`text`]: human-editable format for authoring levels in a text editor.
+//! - [`replay`]: binary format for [replays](crate::world::replay).
+//! - [`savestate`]: binary format for [savestates](crate::world::savestate).
+
+pub mod binary;
+pub mod replay;
+pub mod savestate;
+pub mod text;
+
+use std::{
+    io::{ErrorKind, Read, Write},
+    time::Duration,
+};
+
+use crate::{
+    content::block::state::StateError,
+    world::{Quat, math},
+};
+
+/// An error that might occur when loading a [`Level`](crate::world::Level) or similar data from a
+/// file.
+#[derive(Debug)]
+pub enum LoadError {
+    /// The underlying reader failed.
+    Io(std::io::Error),
+
+    /// The data ended before a complete value could be read.
+    Truncated,
+
+    /// The data does not start with the expected signature, so it is likely not a file of this
+    /// format at all.
+    BadMagic,
+
+    /// The data was written in a version of the format that is not supported.
+    UnsupportedVersion {
+        /// The version declared by the data.
+        found: u16,
+    },
+
+    /// A block refers to a kind that is not known.
+    UnknownKind {
+        /// The name of the kind as found in the data.
+        name: String,
+    },
+
+    /// Some data violates the format.
+    Malformed {
+        /// A human-readable description of the problem.
+        reason: String,
+    },
+
+    /// Text input violates the format at a specific location.
+    Syntax {
+        /// The 1-based number of the offending line.
+        line: usize,
+
+        /// The 1-based number of the offending character in the line.
+        column: usize,
+
+        /// A human-readable description of the problem.
+        reason: String,
+    },
+}
+
+impl LoadError {
+    /// Shorthand for a [`LoadError::Malformed`] with given reason.
+    pub fn malformed(reason: impl Into<String>) -> Self {
+        Self::Malformed {
+            reason: reason.into(),
+        }
+    }
+}
+
+impl From<std::io::Error> for LoadError {
+    fn from(error: std::io::Error) -> Self {
+        if error.kind() == std::io::ErrorKind::UnexpectedEof {
+            Self::Truncated
+        } else {
+            Self::Io(error)
+        }
+    }
+}
+
+impl From<StateError> for LoadError {
+    fn from(error: StateError) -> Self {
+        match error {
+            StateError::UnknownKind { kind } => Self::UnknownKind { name: kind },
+            error => Self::malformed(error.to_string()),
+        }
+    }
+}
+
+impl std::fmt::Display for LoadError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Io(error) => write!(f, "I/O error: {error}"),
+            Self::Truncated => write!(f, "unexpected end of data"),
+            Self::BadMagic => write!(f, "not a file of the expected format"),
+            Self::UnsupportedVersion { found } => write!(f, "unsupported format version {found}"),
+            Self::UnknownKind { name } => write!(f, "unknown block kind {name:?}"),
+            Self::Malformed { reason } => write!(f, "malformed data: {reason}"),
+            Self::Syntax {
+                line,
+                column,
+                reason,
+            } => write!(f, "line {line}, column {column}: {reason}"),
+        }
+    }
+}
+
+impl std::error::Error for LoadError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::Io(error) => Some(error),
+            _ => None,
+        }
+    }
+}
+
+/// Maximum number of blocks in a loaded level.
+///
+/// Enforced when loading to avoid huge allocations caused by corrupted files.
+pub const MAX_LEVEL_VOLUME: u64 = 1 << 24;
+
+/// Maximum length in bytes of the name, author and description of a level.
+///
+/// This is the longest string the binary formats can store. Enforced when loading text so that
+/// every level that loads can also be saved.
+pub const MAX_METADATA_LENGTH: usize = u16::MAX as usize;
+
+/// Little-endian decoding of primitive values used by binary formats.
+pub(crate) trait ReadExt: Read {
+    fn read_u8(&mut self) -> std::io::Result<u8> {
+        let mut buf = [0; 1];
+        self.read_exact(&mut buf)?;
+        Ok(buf[0])
+    }
+
+    fn read_u16(&mut self) -> std::io::Result<u16> {
+        let mut buf = [0; 2];
+        self.read_exact(&mut buf)?;
+        Ok(u16::from_le_bytes(buf))
+    }
+
+    fn read_u32(&mut self) -> std::io::Result<u32> {
+        let mut buf = [0; 4];
+        self.read_exact(&mut buf)?;
+        Ok(u32::from_le_bytes(buf))
+    }
+
+    fn read_u64(&mut self) -> std::io::Result<u64> {
+        let mut buf = [0; 8];
+        self.read_exact(&mut buf)?;
+        Ok(u64::from_le_bytes(buf))
+    }
+
+    /// Read an `f32` bit-exactly.
+    fn read_f32(&mut self) -> std::io::Result<f32> {
+        Ok(f32::from_bits(self.read_u32()?))
+    }
+
+    /// Read a flag stored as a `u8` that is `1` if set and `0` otherwise.
+    fn read_bool(&mut self) -> Result<bool, LoadError> {
+        match self.read_u8()? {
+            0 => Ok(false),
+            1 => Ok(true),
+            value => Err(LoadError::malformed(format!("invalid flag {value}"))),
+        }
+    }
+
+    /// Read a duration stored as whole seconds as `u64` followed by the fractional part in
+    /// nanoseconds as `u32`.
+    fn read_duration(&mut self) -> Result<Duration, LoadError> {
+        let secs = self.read_u64()?;
+        let nanos = self.read_u32()?;
+        if nanos >= 1_000_000_000 {
+            return Err(LoadError::malformed(format!(
+                "duration has {nanos} nanoseconds"
+            )));
+        }
+        Ok(Duration::new(secs, nanos))
+    }
+
+    /// Read a rotation stored as the X, Y, Z and W components of a quaternion as `f32`s and
+    /// [normalize](math::normalize) it.
+    fn read_rotation(&mut self) -> Result<Quat, LoadError> {
+        let q = Quat::from_xyzw(
+            self.read_f32()?,
+            self.read_f32()?,
+            self.read_f32()?,
+            self.read_f32()?,
+        );
+        math::normalize(q).ok_or_else(|| LoadError::malformed(format!("invalid rotation {q}")))
+    }
+
+    /// Read a string prefixed with its length in bytes as a `u8`.
+    fn read_short_str(&mut self) -> Result<String, LoadError> {
+        let mut buf = vec![0; self.read_u8()? as usize];
+        self.read_exact(&mut buf)?;
+        String::from_utf8(buf).map_err(|_| LoadError::malformed("string is not valid UTF-8"))
+    }
+
+    /// Read a string prefixed with its length in bytes as a `u16`.
+    fn read_str(&mut self) -> Result<String, LoadError> {
+        let mut buf = vec![0; self.read_u16()? as usize];
+        self.read_exact(&mut buf)?;
+        String::from_utf8(buf).map_err(|_| LoadError::malformed("string is not valid UTF-8"))
+    }
+
+    /// Read and check a fixed signature.
+    fn expect_magic(&mut self, magic: &[u8]) -> Result<(), LoadError> {
+        let mut buf = vec![0; magic.len()];
+        self.read_exact(&mut buf)?;
+        if buf == magic {
+            Ok(())
+        } else {
+            Err(LoadError::BadMagic)
+        }
+    }
+}
+
+impl<R: Read + ?Sized> ReadExt for R {}
+
+/// Little-endian encoding of primitive values used by binary formats.
+pub(crate) trait WriteExt: Write {
+    fn write_u8(&mut self, value: u8) -> std::io::Result<()> {
+        self.write_all(&[value])
+    }
+
+    fn write_u16(&mut self, value: u16) -> std::io::Result<()> {
+        self.write_all(&value.to_le_bytes())
+    }
+
+    fn write_u32(&mut self, value: u32) -> std::io::Result<()> {
+        self.write_all(&value.to_le_bytes())
+    }
+
+    fn write_u64(&mut self, value: u64) -> std::io::Result<()> {
+        self.write_all(&value.to_le_bytes())
+    }
+
+    /// Write an `f32` bit-exactly.
+    fn write_f32(&mut self, value: f32) -> std::io::Result<()> {
+        self.write_u32(value.to_bits())
+    }
+
+    /// Write a flag as a `u8` that is `1` if set and `0` otherwise.
+    fn write_bool(&mut self, value: bool) -> std::io::Result<()> {
+        self.write_u8(value as u8)
+    }
+
+    /// Write a duration as whole seconds as `u64` followed by the fractional part in nanoseconds
+    /// as `u32`.
+    fn write_duration(&mut self, value: Duration) -> std::io::Result<()> {
+        self.write_u64(value.as_secs())?;
+        self.write_u32(value.subsec_nanos())
+    }
+
+    /// Write a rotation as the X, Y, Z and W components of a quaternion as `f32`s.
+    fn write_rotation(&mut self, value: Quat) -> std::io::Result<()> {
+        for component in value.to_array() {
+            self.write_f32(component)?;
+        }
+        Ok(())
+    }
+
+    /// Write a string prefixed with its length in bytes as a `u8`.
+    ///
+    /// # Errors
+    /// Fails with [`ErrorKind::InvalidInput`] if _value_ is longer than 255 bytes.
+    fn write_short_str(&mut self, value: &str) -> std::io::Result<()> {
+        let len: u8 = value
+            .len()
+            .try_into()
+            .map_err(|_| invalid_input("string is longer than 255 bytes"))?;
+        self.write_u8(len)?;
+        self.write_all(value.as_bytes())
+    }
+
+    /// Write a string prefixed with its length in bytes as a `u16`.
+    ///
+    /// # Errors
+    /// Fails with [`ErrorKind::InvalidInput`] if _value_ is longer than 65535 bytes.
+    fn write_str(&mut self, value: &str) -> std::io::Result<()> {
+        let len: u16 = value
+            .len()
+            .try_into()
+            .map_err(|_| invalid_input("string is longer than 65535 bytes"))?;
+        self.write_u16(len)?;
+        self.write_all(value.as_bytes())
+    }
+}
+
+impl<W: Write + ?Sized> WriteExt for W {}
+
+/// Create the error returned when a value cannot be represented in a binary format.
+pub(crate) fn invalid_input(message: &str) -> std::io::Error {
+    std::io::Error::new(ErrorKind::InvalidInput, message)
+}
@@ -0,0 +1,427 @@
+//! Compact binary level format.
+//!
+//! All numbers are little-endian; floats are stored bit-exactly. A file consists of:
+//! - magic bytes [`MAGIC`],
+//! - format version as `u16`, see [`VERSION`],
+//! - level position as three finite `f32`s,
+//! - level rotation as the X, Y, Z and W components of a unit quaternion as `f32`s,
+//! - the [metadata](Metadata) as described below,
+//! - level shape as three `u32`s,
+//! - the palette: number of entries as `u16`, then the kind name of each entry as a string
+//!   prefixed with its `u8` length,
+//! - for each block in [`Array3::pos_iter`](crate::world::array3::Array3::pos_iter) order, the
+//!   block encoded as described below.
+//!
+//! A block is encoded as a `u16` index into the palette followed by its [serialized](Serialized)
+//! state: the `u32` state, the number of nested blocks as `u8` and each nested block encoded
+//! recursively.
+//!
+//! Metadata is encoded as:
+//! - the name, author and description, each as a string prefixed with its `u16` length,
+//! - a `u8` that is `1` if the level declares a spawn point and `0` otherwise, followed by the
+//!   spawn position as three finite `f32`s and its yaw as a finite `f32` if it does,
+//! - the par time followed by the gold, silver and bronze medal times, each as a `u8` that is `1`
+//!   if the time is set and `0` otherwise, followed by whole seconds as `u64` and the fractional
+//!   part in nanoseconds as `u32` if it is.
+
+use std::{
+    io::{Read, Write},
+    time::Duration,
+};
+
+use crate::{
+    content::block::{Block, MAX_NESTING_DEPTH, Nested, Serialized},
+    world::{
+        Level, UVec3, Vec3,
+        array3::Array3,
+        format::{LoadError, MAX_LEVEL_VOLUME, ReadExt, WriteExt, invalid_input},
+        metadata::{Metadata, Spawn},
+    },
+};
+
+/// Signature that every binary level file starts with.
+pub const MAGIC: &[u8; 8] = b"TRAPLVL\0";
+
+/// The version of the format written by [`Level::save`]. Only this version can be loaded.
+pub const VERSION: u16 = 1;
+
+/// Decode a single block, including its kind.
+///
+/// _depth_ is the number of blocks this block is nested in.
+fn read_block(
+    input: &mut impl Read,
+    palette: &[String],
+    depth: usize,
+) -> Result<Nested, LoadError> {
+    if depth > MAX_NESTING_DEPTH {
+        return Err(LoadError::malformed("blocks are nested too deeply"));
+    }
+
+    let index = input.read_u16()?;
+    let kind = palette
+        .get(index as usize)
+        .ok_or_else(|| LoadError::malformed(format!("palette index {index} is out of bounds")))?;
+
+    let mut data = Serialized::new(input.read_u32()?);
+    for _ in 0..input.read_u8()? {
+        data.nested.push(read_block(input, palette, depth + 1)?);
+    }
+
+    Ok(Nested {
+        kind: kind.clone(),
+        data,
+    })
+}
+
+/// Encode a single block, including its kind.
+fn write_block(output: &mut impl Write, palette: &[&str], block: &Nested) -> std::io::Result<()> {
+    let index = palette.iter().position(|n| *n == block.kind).unwrap();
+    output.write_u16(index as u16)?;
+    output.write_u32(block.data.state)?;
+
+    let count: u8 = block
+        .data
+        .nested
+        .len()
+        .try_into()
+        .map_err(|_| invalid_input("block has more than 255 nested blocks"))?;
+    output.write_u8(count)?;
+    for nested in &block.data.nested {
+        write_block(output, palette, nested)?;
+    }
+
+    Ok(())
+}
+
+/// Add the kind of _block_ and of all blocks nested in it to _palette_ unless already present.
+fn collect_palette<'a>(palette: &mut Vec<&'a str>, block: &'a Nested) {
+    if !palette.contains(&block.kind.as_str()) {
+        palette.push(&block.kind);
+    }
+    for nested in &block.data.nested {
+        collect_palette(palette, nested);
+    }
+}
+
+/// Decode an optional duration.
+fn read_optional_duration(input: &mut impl Read) -> Result<Option<Duration>, LoadError> {
+    if input.read_bool()? {
+        Ok(Some(input.read_duration()?))
+    } else {
+        Ok(None)
+    }
+}
+
+/// Encode an optional duration.
+fn write_optional_duration(
+    output: &mut impl Write,
+    value: Option<Duration>,
+) -> std::io::Result<()> {
+    output.write_bool(value.is_some())?;
+    match value {
+        Some(value) => output.write_duration(value),
+        None => Ok(()),
+    }
+}
+
+/// Decode level metadata other than the format version.
+pub(super) fn read_metadata(input: &mut impl Read) -> Result<Metadata, LoadError> {
+    let mut metadata = Metadata {
+        name: input.read_str()?,
+        author: input.read_str()?,
+        description: input.read_str()?,
+        ..Metadata::default()
+    };
+
+    if input.read_bool()? {
+        let spawn = Spawn {
+            position: Vec3::new(input.read_f32()?, input.read_f32()?, input.read_f32()?),
+            yaw: input.read_f32()?,
+        };
+        if !spawn.position.is_finite() || !spawn.yaw.is_finite() {
+            return Err(LoadError::malformed(format!(
+                "spawn point {} with yaw {} is not finite",
+                spawn.position, spawn.yaw
+            )));
+        }
+        metadata.spawn = Some(spawn);
+    }
+
+    metadata.par_time = read_optional_duration(input)?;
+    for time in &mut metadata.medal_times {
+        *time = read_optional_duration(input)?;
+    }
+
+    Ok(metadata)
+}
+
+/// Encode level metadata other than the format version.
+///
+/// # Errors
+/// Fails with [`InvalidInput`](std::io::ErrorKind::InvalidInput) if the name, author or
+/// description is longer than [`MAX_METADATA_LENGTH`](crate::world::format::MAX_METADATA_LENGTH)
+/// bytes.
+pub(super) fn write_metadata(output: &mut impl Write, metadata: &Metadata) -> std::io::Result<()> {
+    output.write_str(&metadata.name)?;
+    output.write_str(&metadata.author)?;
+    output.write_str(&metadata.description)?;
+
+    output.write_bool(metadata.spawn.is_some())?;
+    if let Some(spawn) = &metadata.spawn {
+        for coord in spawn.position.to_array() {
+            output.write_f32(coord)?;
+        }
+        output.write_f32(spawn.yaw)?;
+    }
+
+    write_optional_duration(output, metadata.par_time)?;
+    for time in metadata.medal_times {
+        write_optional_duration(output, time)?;
+    }
+
+    Ok(())
+}
+
+/// Decode a block grid, i.e. its shape, palette and blocks.
+pub(super) fn read_blocks(input: &mut impl Read) -> Result<Array3<Block>, LoadError> {
+    let shape = UVec3::new(input.read_u32()?, input.read_u32()?, input.read_u32()?);
+    let volume = shape.as_u64vec3().element_product();
+    if volume > MAX_LEVEL_VOLUME {
+        return Err(LoadError::malformed(format!(
+            "level shape {shape} is too large"
+        )));
+    }
+
+    let palette = (0..input.read_u16()?)
+        .map(|_| input.read_short_str())
+        .collect::<Result<Vec<_>, _>>()?;
+
+    // Grow as blocks are read so that a corrupted shape cannot reserve memory for blocks that the
+    // input does not contain
+    let mut data = Vec::new();
+    for _ in 0..volume {
+        let block = read_block(input, &palette, 0)?;
+        data.push(Block::deserialize(&block.kind, &block.data)?);
+    }
+
+    Ok(Array3::from_vec(shape, data).unwrap())
+}
+
+/// Encode the shape, palette and blocks of _blocks_.
+pub(super) fn write_blocks(output: &mut impl Write, blocks: &Array3<Block>) -> std::io::Result<()> {
+    let nested: Vec<Nested> = blocks
+        .into_iter()
+        .map(|block| block.serialize_nested())
+        .collect();
+
+    let mut palette = Vec::new();
+    for block in &nested {
+        collect_palette(&mut palette, block);
+    }
+
+    for dim in blocks.shape().to_array() {
+        output.write_u32(dim)?;
+    }
+
+    output.write_u16(palette.len() as u16)?;
+    for name in &palette {
+        output.write_short_str(name)?;
+    }
+
+    for block in &nested {
+        write_block(output, &palette, block)?;
+    }
+
+    Ok(())
+}
+
+impl Level {
+    /// Load a level in [binary format](self) from _input_.
+    pub fn load(input: &mut impl Read) -> Result<Self, LoadError> {
+        input.expect_magic(MAGIC)?;
+
+        let version = input.read_u16()?;
+        if version != VERSION {
+            return Err(LoadError::UnsupportedVersion { found: version });
+        }
+
+        let position = Vec3::new(input.read_f32()?, input.read_f32()?, input.read_f32()?);
+        if !position.is_finite() {
+            return Err(LoadError::malformed(format!(
+                "level position {position} is not finite"
+            )));
+        }
+        let rotation = input.read_rotation()?;
+
+        let mut metadata = read_metadata(input)?;
+        metadata.format_version = version;
+
+        let blocks = read_blocks(input)?;
+        let mut level = Self::from_blocks(blocks, position, rotation);
+        level.metadata = metadata;
+        Ok(level)
+    }
+
+    /// Write this level in [binary format](self) to _output_.
+    ///
+    /// # Errors
+    /// Fails with [`InvalidInput`](std::io::ErrorKind::InvalidInput) if the name, author or
+    /// description of the level is longer than 65535 bytes or a block has more than 255 nested
+    /// blocks.
+    pub fn save(&self, output: &mut impl Write) -> std::io::Result<()> {
+        output.write_all(MAGIC)?;
+        output.write_u16(VERSION)?;
+
+        for coord in self.position.to_array() {
+            output.write_f32(coord)?;
+        }
+        output.write_rotation(self.rotation)?;
+
+        write_metadata(output, &self.metadata)?;
+        write_blocks(output, &self.blocks)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::ErrorKind;
+
+    use super::*;
+    use crate::{
+        content::block::state,
+        world::{Quat, math},
+    };
+
+    fn sample() -> Level {
+        let mut blocks = Array3::default(UVec3::new(3, 2, 2));
+        blocks[UVec3::new(1, 1, 1)] = state::parse("stone").unwrap();
+        blocks[UVec3::new(2, 0, 1)] =
+            state::parse("pusher[facing=north,contents=pusher[contents=sand]]").unwrap();
+
+        let mut level =
+            Level::from_blocks(blocks, Vec3::new(1.0, -2.5, 3.0), math::rotation_z(-0.3));
+        level.metadata = Metadata {
+            name: "Sample".to_string(),
+            author: "Someone".to_string(),
+            description: "Line one\n\nLine three".to_string(),
+            spawn: Some(Spawn {
+                position: Vec3::new(0.5, 1.0, 1.5),
+                yaw: 0.25,
+            }),
+            par_time: Some(Duration::new(12, 500_000_000)),
+            medal_times: [
+                Some(Duration::from_secs(10)),
+                None,
+                Some(Duration::new(20, 1)),
+            ],
+            format_version: VERSION,
+        };
+        level
+    }
+
+    fn save(level: &Level) -> Vec<u8> {
+        let mut output = Vec::new();
+        level.save(&mut output).unwrap();
+        output
+    }
+
+    /// Start a file with a level at _position_ and empty metadata, up to its blocks.
+    fn header(position: [f32; 3]) -> Vec<u8> {
+        let mut output = Vec::new();
+        output.write_all(MAGIC).unwrap();
+        output.write_u16(VERSION).unwrap();
+        for coord in position {
+            output.write_f32(coord).unwrap();
+        }
+        output.write_rotation(Quat::IDENTITY).unwrap();
+        write_metadata(&mut output, &Metadata::default()).unwrap();
+        output
+    }
+
+    #[test]
+    fn round_trip_is_exact() {
+        let level = sample();
+        let data = save(&level);
+        let loaded = Level::load(&mut &data[..]).unwrap();
+
+        assert_eq!(save(&loaded), data);
+        assert_eq!(loaded.metadata, level.metadata);
+        assert_eq!(loaded.position, level.position);
+        assert_eq!(loaded.rotation, level.rotation);
+        for (a, b) in level.blocks.into_iter().zip(&loaded.blocks) {
+            assert_eq!(a.to_string(), b.to_string());
+        }
+    }
+
+    #[test]
+    fn corrupt_input_is_rejected() {
+        let data = save(&sample());
+
+        let result = Level::load(&mut &data[..data.len() - 1]);
+        assert!(matches!(result, Err(LoadError::Truncated)));
+
+        let mut bad = data.clone();
+        bad[0] = b'X';
+        assert!(matches!(
+            Level::load(&mut &bad[..]),
+            Err(LoadError::BadMagic)
+        ));
+
+        let mut bad = data.clone();
+        bad[8] = 9;
+        let result = Level::load(&mut &bad[..]);
+        assert!(matches!(
+            result,
+            Err(LoadError::UnsupportedVersion { found: 9 })
+        ));
+
+        let bad = header([0.0, f32::NAN, 0.0]);
+        assert!(matches!(
+            Level::load(&mut &bad[..]),
+            Err(LoadError::Malformed { .. })
+        ));
+
+        let mut bad = header([0.0; 3]);
+        for dim in [1 << 10, 1 << 10, 1 << 10] {
+            bad.write_u32(dim).unwrap();
+        }
+        assert!(matches!(
+            Level::load(&mut &bad[..]),
+            Err(LoadError::Malformed { .. })
+        ));
+
+        // A single block with a palette index past the end of the palette
+        let mut bad = header([0.0; 3]);
+        for dim in [1, 1, 1] {
+            bad.write_u32(dim).unwrap();
+        }
+        bad.write_u16(1).unwrap();
+        bad.write_short_str("stone").unwrap();
+        let mut deep = bad.clone();
+        bad.write_u16(1).unwrap();
+        assert!(matches!(
+            Level::load(&mut &bad[..]),
+            Err(LoadError::Malformed { .. })
+        ));
+
+        // A single block with blocks nested in each other too deeply
+        for _ in 0..=MAX_NESTING_DEPTH + 1 {
+            deep.write_u16(0).unwrap();
+            deep.write_u32(0).unwrap();
+            deep.write_u8(1).unwrap();
+        }
+        assert!(matches!(
+            Level::load(&mut &deep[..]),
+            Err(LoadError::Malformed { .. })
+        ));
+    }
+
+    #[test]
+    fn overlong_metadata_is_invalid_input() {
+        let mut level = sample();
+        level.metadata.author = "x".repeat(crate::world::format::MAX_METADATA_LENGTH + 1);
+
+        let error = level.save(&mut Vec::new()).unwrap_err();
+        assert_eq!(error.kind(), ErrorKind::InvalidInput);
+    }
+}
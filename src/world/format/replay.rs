@@ -0,0 +1,274 @@
+//! Binary replay format.
+//!
+//! All numbers are little-endian; floats are stored bit-exactly so that playback reproduces the
+//! recorded run exactly. A file consists of:
+//! - magic bytes [`MAGIC`],
+//! - format version as `u16`, see [`VERSION`],
+//! - the [level reference](Replay::level) as a string prefixed with its `u16` length,
+//! - the number of events as `u32`,
+//! - each event encoded as described below,
+//! - the number of [checksums](crate::world::replay::Checksum) as `u32`,
+//! - for each checksum, its position as `u32` and hash as `u64`.
+//!
+//! Checksums must be ordered by position and not exceed the number of events.
+//!
+//! An event is encoded as a `u8` tag followed by its fields:
+//! - `0`: [`Event::LogicTick`], no fields,
+//! - `1`: [`Event::PresentationTick`], whole seconds of the duration as `u64` and the fractional
+//!   part in nanoseconds as `u32`; durations longer than [`MAX_PRESENTATION_TICK_DURATION`] are
+//!   rejected,
+//! - `2`: [`Event::MovePlayerCharacter`], direction as two `f32`s,
+//! - `3`: [`Event::SetPlayerCharacterRotation`], yaw and pitch as `f32`s,
+//! - `4`: [`Event::JumpPlayerCharacter`], no fields,
+//! - `5`: [`Event::UseBlock`], direction as three `f32`s,
+//! - `6`: [`Event::RespawnPlayerCharacter`], no fields,
+//! - `7`: [`Event::Restart`], no fields,
+//! - `8`: [`Event::LoadSavestate`], the savestate in [savestate
+//!   format](crate::world::format::savestate).
+
+use std::{
+    io::{Read, Write},
+    sync::Arc,
+};
+
+use crate::world::{
+    Event, MAX_PRESENTATION_TICK_DURATION, Vec2, Vec3, YawPitch,
+    format::{LoadError, ReadExt, WriteExt, invalid_input},
+    replay::{Checksum, Replay},
+    savestate::Savestate,
+};
+
+/// Signature that every replay file starts with.
+pub const MAGIC: &[u8; 8] = b"TRAPRPL\0";
+
+/// The version of the format written by [`Replay::save`] and the only version that can be loaded.
+pub const VERSION: u16 = 1;
+
+impl Event {
+    /// Decode a single event in [replay format](self) from _input_.
+    pub fn read(input: &mut impl Read) -> Result<Self, LoadError> {
+        Ok(match input.read_u8()? {
+            0 => Self::LogicTick,
+            1 => {
+                let duration = input.read_duration()?;
+                if duration > MAX_PRESENTATION_TICK_DURATION {
+                    return Err(LoadError::malformed(format!(
+                        "presentation tick duration {duration:?} is too long"
+                    )));
+                }
+                Self::PresentationTick { duration }
+            }
+            2 => Self::MovePlayerCharacter {
+                direction: Vec2::new(input.read_f32()?, input.read_f32()?),
+            },
+            3 => Self::SetPlayerCharacterRotation {
+                rotation: YawPitch {
+                    yaw: input.read_f32()?,
+                    pitch: input.read_f32()?,
+                },
+            },
+            4 => Self::JumpPlayerCharacter,
+            5 => Self::UseBlock {
+                direction: Vec3::new(input.read_f32()?, input.read_f32()?, input.read_f32()?),
+            },
+            6 => Self::RespawnPlayerCharacter,
+            7 => Self::Restart,
+            8 => Self::LoadSavestate {
+                savestate: Arc::new(Savestate::load(input)?),
+            },
+            tag => return Err(LoadError::malformed(format!("unknown event tag {tag}"))),
+        })
+    }
+
+    /// Encode this event in [replay format](self) to _output_.
+    pub fn write(&self, output: &mut impl Write) -> std::io::Result<()> {
+        match self {
+            Self::LogicTick => output.write_u8(0),
+            Self::PresentationTick { duration } => {
+                output.write_u8(1)?;
+                output.write_duration(*duration)
+            }
+            Self::MovePlayerCharacter { direction } => {
+                output.write_u8(2)?;
+                for coord in direction.to_array() {
+                    output.write_f32(coord)?;
+                }
+                Ok(())
+            }
+            Self::SetPlayerCharacterRotation { rotation } => {
+                output.write_u8(3)?;
+                output.write_f32(rotation.yaw)?;
+                output.write_f32(rotation.pitch)
+            }
+            Self::JumpPlayerCharacter => output.write_u8(4),
+            Self::UseBlock { direction } => {
+                output.write_u8(5)?;
+                for coord in direction.to_array() {
+                    output.write_f32(coord)?;
+                }
+                Ok(())
+            }
+            Self::RespawnPlayerCharacter => output.write_u8(6),
+            Self::Restart => output.write_u8(7),
+            Self::LoadSavestate { savestate } => {
+                output.write_u8(8)?;
+                savestate.save(output)
+            }
+        }
+    }
+}
+
+impl Replay {
+    /// Load a replay in [replay format](self) from _input_.
+    pub fn load(input: &mut impl Read) -> Result<Self, LoadError> {
+        input.expect_magic(MAGIC)?;
+
+        let version = input.read_u16()?;
+        if version != VERSION {
+            return Err(LoadError::UnsupportedVersion { found: version });
+        }
+
+        let mut replay = Self::new(input.read_str()?);
+        for _ in 0..input.read_u32()? {
+            replay.events.push(Event::read(input)?);
+        }
+
+        for _ in 0..input.read_u32()? {
+            let checksum = Checksum {
+                position: input.read_u32()? as usize,
+                hash: input.read_u64()?,
+            };
+
+            let previous = replay.checksums.last().map_or(0, |c| c.position);
+            if checksum.position < previous || checksum.position > replay.events.len() {
+                return Err(LoadError::malformed(format!(
+                    "checksum position {} is out of order or range",
+                    checksum.position
+                )));
+            }
+            replay.checksums.push(checksum);
+        }
+
+        Ok(replay)
+    }
+
+    /// Write this replay in [replay format](self) to _output_.
+    ///
+    /// # Errors
+    /// Fails with [`InvalidInput`](std::io::ErrorKind::InvalidInput) if the level reference is
+    /// longer than 65535 bytes or there are more than [`u32::MAX`] events or checksums.
+    pub fn save(&self, output: &mut impl Write) -> std::io::Result<()> {
+        output.write_all(MAGIC)?;
+        output.write_u16(VERSION)?;
+        output.write_str(&self.level)?;
+
+        let count: u32 = self
+            .events
+            .len()
+            .try_into()
+            .map_err(|_| invalid_input("replay has more than u32::MAX events"))?;
+        output.write_u32(count)?;
+        for event in &self.events {
+            event.write(output)?;
+        }
+
+        let count: u32 = self
+            .checksums
+            .len()
+            .try_into()
+            .map_err(|_| invalid_input("replay has more than u32::MAX checksums"))?;
+        output.write_u32(count)?;
+        for checksum in &self.checksums {
+            output.write_u32(checksum.position as u32)?;
+            output.write_u64(checksum.hash)?;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+    use crate::world::{Level, World, replay::TEST_LEVEL};
+
+    fn save(replay: &Replay) -> Vec<u8> {
+        let mut output = Vec::new();
+        replay.save(&mut output).unwrap();
+        output
+    }
+
+    #[test]
+    fn round_trip_is_exact() {
+        let world = World::from_levels(vec![Level::test()]);
+        let mut replay = Replay::new(TEST_LEVEL);
+        for event in [
+            Event::MovePlayerCharacter {
+                direction: Vec2::new(0.3, -0.0),
+            },
+            Event::SetPlayerCharacterRotation {
+                rotation: YawPitch {
+                    yaw: 0.123,
+                    pitch: -1.1,
+                },
+            },
+            Event::JumpPlayerCharacter,
+            Event::PresentationTick {
+                duration: Duration::new(0, 16_666_667),
+            },
+            Event::LogicTick,
+            Event::UseBlock {
+                direction: Vec3::new(1.0, 0.0, -0.5),
+            },
+            Event::RespawnPlayerCharacter,
+            Event::Restart,
+            Event::LoadSavestate {
+                savestate: Arc::new(Savestate::capture(&world)),
+            },
+        ] {
+            replay.record(&event);
+        }
+        replay.record_checksum(&world);
+
+        let data = save(&replay);
+        let loaded = Replay::load(&mut &data[..]).unwrap();
+        assert_eq!(loaded.level, replay.level);
+        assert_eq!(loaded.events.len(), replay.events.len());
+        assert_eq!(loaded.checksums, replay.checksums);
+        assert_eq!(save(&loaded), data);
+    }
+
+    #[test]
+    fn corrupt_input_is_rejected() {
+        let mut replay = Replay::new(TEST_LEVEL);
+        replay.record(&Event::LogicTick);
+        let data = save(&replay);
+
+        let result = Replay::load(&mut &data[..data.len() - 1]);
+        assert!(matches!(result, Err(LoadError::Truncated)));
+
+        let mut bad = data.clone();
+        bad[8] = 9;
+        let result = Replay::load(&mut &bad[..]);
+        assert!(matches!(
+            result,
+            Err(LoadError::UnsupportedVersion { found: 9 })
+        ));
+
+        let mut bad = Vec::new();
+        Event::PresentationTick {
+            duration: MAX_PRESENTATION_TICK_DURATION + Duration::from_nanos(1),
+        }
+        .write(&mut bad)
+        .unwrap();
+        assert!(matches!(
+            Event::read(&mut &bad[..]),
+            Err(LoadError::Malformed { .. })
+        ));
+
+        let result = Event::read(&mut &[99][..]);
+        assert!(matches!(result, Err(LoadError::Malformed { .. })));
+    }
+}
@@ -0,0 +1,350 @@
+//! Binary savestate format.
+//!
+//! All numbers are little-endian; floats are stored bit-exactly so that loading restores the state
+//! exactly. Flags are stored as a `u8` that is `1` if set and `0` otherwise. A file consists of:
+//! - magic bytes [`MAGIC`],
+//! - format version as `u16`, see [`VERSION`],
+//! - the number of levels as `u32`,
+//! - each level encoded as described below,
+//! - the player character: position, velocity as three `f32`s each, yaw and pitch as `f32`s,
+//!   movement controls as two `f32`s, the grounded flag, the friction of the surface it stands on
+//!   as `f32` and the alive flag,
+//! - the run: its state as `u8` (`0` not started, `1` running, `2` finished), whole seconds of its
+//!   time as `u64` and the fractional part in nanoseconds as `u32`, logic ticks as `u64`, respawns
+//!   as `u32` and the practice flag,
+//! - the current checkpoint and the spawn point, each as position as three `f32`s followed by yaw
+//!   and pitch as `f32`s.
+//!
+//! A level is encoded as:
+//! - position as three `f32`s,
+//! - rotation as the X, Y, Z and W components of a unit quaternion as `f32`s,
+//! - its [metadata](crate::world::metadata::Metadata) like in the [binary level
+//!   format](crate::world::format::binary), followed by the format version it was read from as
+//!   `u16`,
+//! - its current blocks and its initial blocks, each as shape, palette and blocks like in the
+//!   [binary level format](crate::world::format::binary),
+//! - the number of logic ticks it has received as `u64`,
+//! - the number of pending block updates as `u32`, then for each update in execution order its due
+//!   tick as `u64`, position as three `i32`s and [cause code](update::Cause::code) as `u8`.
+
+use std::{
+    io::{Read, Write},
+    sync::Arc,
+};
+
+use crate::world::{
+    IVec3, Level, Vec2, Vec3, World, YawPitch,
+    character::Character,
+    format::{LoadError, ReadExt, WriteExt, binary, invalid_input},
+    respawn::Checkpoint,
+    run::{Run, RunState},
+    savestate::Savestate,
+    update::{self, Update},
+};
+
+/// Signature that every savestate file starts with.
+pub const MAGIC: &[u8; 8] = b"TRAPSAV\0";
+
+/// The version of the format written by [`Savestate::save`] and the only version that can be
+/// loaded.
+pub const VERSION: u16 = 1;
+
+fn read_vec3(input: &mut impl Read) -> std::io::Result<Vec3> {
+    Ok(Vec3::new(
+        input.read_f32()?,
+        input.read_f32()?,
+        input.read_f32()?,
+    ))
+}
+
+fn write_vec3(output: &mut impl Write, value: Vec3) -> std::io::Result<()> {
+    for coord in value.to_array() {
+        output.write_f32(coord)?;
+    }
+    Ok(())
+}
+
+fn read_checkpoint(input: &mut impl Read) -> std::io::Result<Checkpoint> {
+    Ok(Checkpoint {
+        position: read_vec3(input)?,
+        rotation: YawPitch {
+            yaw: input.read_f32()?,
+            pitch: input.read_f32()?,
+        },
+    })
+}
+
+fn write_checkpoint(output: &mut impl Write, checkpoint: &Checkpoint) -> std::io::Result<()> {
+    write_vec3(output, checkpoint.position)?;
+    output.write_f32(checkpoint.rotation.yaw)?;
+    output.write_f32(checkpoint.rotation.pitch)
+}
+
+/// Decode a level with its initial blocks and pending block updates.
+fn read_level(input: &mut impl Read) -> Result<Level, LoadError> {
+    let position = read_vec3(input)?;
+    let rotation = input.read_rotation()?;
+
+    let mut metadata = binary::read_metadata(input)?;
+    metadata.format_version = input.read_u16()?;
+
+    let blocks = binary::read_blocks(input)?;
+    let initial = binary::read_blocks(input)?;
+    if initial.shape() != blocks.shape() {
+        return Err(LoadError::malformed(format!(
+            "initial shape {} differs from level shape {}",
+            initial.shape(),
+            blocks.shape()
+        )));
+    }
+
+    let tick = input.read_u64()?;
+    let mut pending = Vec::new();
+    for _ in 0..input.read_u32()? {
+        let due = input.read_u64()?;
+        let pos = IVec3::new(
+            input.read_u32()? as i32,
+            input.read_u32()? as i32,
+            input.read_u32()? as i32,
+        );
+        let code = input.read_u8()?;
+        let cause = update::Cause::from_code(code)
+            .ok_or_else(|| LoadError::malformed(format!("unknown update cause {code}")))?;
+        if due <= tick {
+            return Err(LoadError::malformed(format!(
+                "update due in tick {due} is overdue in tick {tick}"
+            )));
+        }
+        pending.push((due, Update { pos, cause }));
+    }
+
+    let mut level = Level::from_blocks(blocks, position, rotation);
+    level.metadata = metadata;
+    level.initial = Arc::new(initial);
+    level.updates = update::Queue::from_pending(tick, pending);
+    Ok(level)
+}
+
+/// Encode a level with its initial blocks and pending block updates.
+fn write_level(output: &mut impl Write, level: &Level) -> std::io::Result<()> {
+    write_vec3(output, level.position)?;
+    output.write_rotation(level.rotation)?;
+
+    binary::write_metadata(output, &level.metadata)?;
+    output.write_u16(level.metadata.format_version)?;
+
+    binary::write_blocks(output, &level.blocks)?;
+    binary::write_blocks(output, level.initial_blocks())?;
+
+    output.write_u64(level.updates.tick())?;
+    let count: u32 = level
+        .updates
+        .iter()
+        .count()
+        .try_into()
+        .map_err(|_| invalid_input("level has more than u32::MAX pending updates"))?;
+    output.write_u32(count)?;
+    for (due, update) in level.updates.iter() {
+        output.write_u64(due)?;
+        for coord in update.pos.to_array() {
+            output.write_u32(coord as u32)?;
+        }
+        output.write_u8(update.cause.code())?;
+    }
+
+    Ok(())
+}
+
+fn read_character(input: &mut impl Read) -> Result<Character, LoadError> {
+    let mut character = Character::new();
+    character.position = read_vec3(input)?;
+    character.velocity = read_vec3(input)?;
+    character.rotation = YawPitch {
+        yaw: input.read_f32()?,
+        pitch: input.read_f32()?,
+    };
+    character.set_control(Vec2::new(input.read_f32()?, input.read_f32()?));
+    character.grounded = input.read_bool()?;
+    character.friction = input.read_f32()?;
+    character.alive = input.read_bool()?;
+    Ok(character)
+}
+
+fn write_character(output: &mut impl Write, character: &Character) -> std::io::Result<()> {
+    write_vec3(output, character.position)?;
+    write_vec3(output, character.velocity)?;
+    output.write_f32(character.rotation.yaw)?;
+    output.write_f32(character.rotation.pitch)?;
+    for coord in character.control().to_array() {
+        output.write_f32(coord)?;
+    }
+    output.write_bool(character.grounded)?;
+    output.write_f32(character.friction)?;
+    output.write_bool(character.alive)
+}
+
+fn read_run(input: &mut impl Read) -> Result<Run, LoadError> {
+    let state = match input.read_u8()? {
+        0 => RunState::NotStarted,
+        1 => RunState::Running,
+        2 => RunState::Finished,
+        state => return Err(LoadError::malformed(format!("unknown run state {state}"))),
+    };
+
+    Ok(Run {
+        state,
+        time: input.read_duration()?,
+        logic_ticks: input.read_u64()?,
+        respawns: input.read_u32()?,
+        practice: input.read_bool()?,
+    })
+}
+
+fn write_run(output: &mut impl Write, run: &Run) -> std::io::Result<()> {
+    output.write_u8(run.state as u8)?;
+    output.write_duration(run.time)?;
+    output.write_u64(run.logic_ticks)?;
+    output.write_u32(run.respawns)?;
+    output.write_bool(run.practice)
+}
+
+impl Savestate {
+    /// Load a savestate in [savestate format](self) from _input_.
+    pub fn load(input: &mut impl Read) -> Result<Self, LoadError> {
+        input.expect_magic(MAGIC)?;
+
+        let version = input.read_u16()?;
+        if version != VERSION {
+            return Err(LoadError::UnsupportedVersion { found: version });
+        }
+
+        let levels = (0..input.read_u32()?)
+            .map(|_| read_level(input))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Ok(Self::new(World {
+            levels,
+            player: read_character(input)?,
+            run: read_run(input)?,
+            checkpoint: read_checkpoint(input)?,
+            spawn: read_checkpoint(input)?,
+        }))
+    }
+
+    /// Write this savestate in [savestate format](self) to _output_.
+    ///
+    /// # Errors
+    /// Fails with [`InvalidInput`](std::io::ErrorKind::InvalidInput) if there are more than
+    /// [`u32::MAX`] levels or pending updates in a level, or if a level does not fit the
+    /// [binary level format](binary).
+    pub fn save(&self, output: &mut impl Write) -> std::io::Result<()> {
+        let world = self.world();
+
+        output.write_all(MAGIC)?;
+        output.write_u16(VERSION)?;
+
+        let count: u32 = world
+            .levels
+            .len()
+            .try_into()
+            .map_err(|_| invalid_input("world has more than u32::MAX levels"))?;
+        output.write_u32(count)?;
+        for level in &world.levels {
+            write_level(output, level)?;
+        }
+
+        write_character(output, &world.player)?;
+        write_run(output, &world.run)?;
+        write_checkpoint(output, &world.checkpoint)?;
+        write_checkpoint(output, &world.spawn)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+    use crate::{
+        content::block::{Block, Sand},
+        logic::Logic,
+        world::{Event, hash::StateHash},
+    };
+
+    fn save(savestate: &Savestate) -> Vec<u8> {
+        let mut output = Vec::new();
+        savestate.save(&mut output).unwrap();
+        output
+    }
+
+    /// The test level in the middle of a run, with falling sand and scheduled updates pending.
+    fn busy_world() -> World {
+        let logic = Logic::new();
+        let mut world = World::from_levels(vec![Level::test()]);
+        let level = &mut world.levels[0];
+        level.set_block(IVec3::new(5, 5, 6), Block::Sand(Sand::default()));
+        level.schedule_update(IVec3::new(1, 1, 1), 50);
+
+        let direction = Vec2::X;
+        world.process(Event::MovePlayerCharacter { direction }, &logic);
+        for tick in 0..12 {
+            let duration = Duration::from_millis(10);
+            world.process(Event::PresentationTick { duration }, &logic);
+            if tick % 5 == 0 {
+                world.process(Event::LogicTick, &logic);
+            }
+        }
+        world
+    }
+
+    #[test]
+    fn round_trip_is_exact() {
+        let world = busy_world();
+        let data = save(&Savestate::capture(&world));
+        let loaded = Savestate::load(&mut &data[..]).unwrap();
+        assert_eq!(loaded.world().checksum(), world.checksum());
+        assert_eq!(save(&loaded), data);
+    }
+
+    #[test]
+    fn corrupt_input_is_rejected() {
+        let data = save(&Savestate::capture(&busy_world()));
+
+        let result = Savestate::load(&mut &data[..data.len() - 1]);
+        assert!(matches!(result, Err(LoadError::Truncated)));
+
+        let mut bad = data.clone();
+        bad[0] = b'X';
+        assert!(Savestate::load(&mut &bad[..]).is_err());
+
+        let mut bad = data;
+        bad[8] = 9;
+        let result = Savestate::load(&mut &bad[..]);
+        assert!(matches!(
+            result,
+            Err(LoadError::UnsupportedVersion { found: 9 })
+        ));
+    }
+
+    #[test]
+    fn loaded_world_continues_identically() {
+        let logic = Logic::new();
+        let mut world = busy_world();
+        let data = save(&Savestate::capture(&world));
+        let savestate = Arc::new(Savestate::load(&mut &data[..]).unwrap());
+
+        let mut practice = World::from_levels(vec![Level::test()]);
+        practice.process(Event::LoadSavestate { savestate }, &logic);
+        assert!(practice.run.practice);
+
+        for world in [&mut world, &mut practice] {
+            for _ in 0..40 {
+                let duration = Duration::from_millis(10);
+                world.process(Event::PresentationTick { duration }, &logic);
+                world.process(Event::LogicTick, &logic);
+            }
+        }
+        practice.run.practice = false;
+        assert_eq!(practice.checksum(), world.checksum());
+    }
+}
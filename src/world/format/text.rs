@@ -0,0 +1,749 @@
+//! Human-editable text level format.
+//!
+//! The format is line-based. Blank lines and lines starting with `;` are ignored. A file consists
+//! of:
+//! - the header line `trapiron-level <version>`, see [`VERSION`],
+//! - properties `size <x> <y> <z>`, `position <x> <y> <z>` and `rotation <x> <y> <z> <w>`, one
+//!   per line in any order. `position` defaults to zero and `rotation`, a quaternion that is
+//!   normalized when parsed, to no rotation. Positions, rotations and spawn points must be
+//!   finite,
+//! - optionally, [metadata](Metadata) properties among the other properties:
+//!   - `name <text>` and `author <text>`, where the text is the rest of the line without
+//!     surrounding whitespace,
+//!   - `description <text>`, which may be repeated to write a description with several lines,
+//!
+//!   The name, author and the description with its line breaks may each be at most
+//!   [`MAX_METADATA_LENGTH`] bytes long,
+//!   - `spawn <x> <y> <z> <yaw>`, the spawn point in level coordinates,
+//!   - `par <seconds>`, `gold <seconds>`, `silver <seconds>` and `bronze <seconds>`, the par and
+//!     medal times as decimal numbers with at most nine fractional digits,
+//! - the line `legend`, followed by one line `<character> <block>` per character used in layers,
+//!   where `<block>` is a [block state description](crate::content::block::state) such as
+//!   `pusher[facing=north,contents=sand]`. The character may be any character other than
+//!   whitespace and `;`, which is reserved for comments because a row starting with it would be
+//!   ignored,
+//! - for each Z coordinate from 0 upwards, the line `layer <z>` followed by one row of characters
+//!   per Y coordinate. The first row has the greatest Y, so that the layer looks like a map with
+//!   X pointing right and Y pointing up. Each row has one character per X coordinate.
+//!
+//! Example:
+//! ```text
+//! trapiron-level 1
+//! size 3 2 2
+//! position 0 5 0
+//! rotation 0 0 0.25881904 0.9659258
+//! name Pusher practice
+//! author Jane Doe
+//! description Push the sand off the edge.
+//! spawn 2 0 1 0
+//! gold 4.5
+//!
+//! legend
+//! . air
+//! # stone
+//! p pusher[facing=north,contents=sand]
+//!
+//! layer 0
+//! ###
+//! ###
+//!
+//! layer 1
+//! p.#
+//! ...
+//! ```
+
+use std::{
+    collections::{HashMap, HashSet},
+    fmt::Write,
+    time::Duration,
+};
+
+use crate::{
+    content::block::state,
+    world::{
+        Float, Level, Quat, UVec3, Vec3,
+        array3::Array3,
+        format::{LoadError, MAX_LEVEL_VOLUME, MAX_METADATA_LENGTH},
+        math,
+        metadata::{Medal, Metadata, Spawn},
+    },
+};
+
+/// The version of the format written by [`Level::to_text`]. Only this version can be parsed.
+pub const VERSION: u16 = 1;
+
+/// Keyword of the header line.
+const HEADER: &str = "trapiron-level";
+
+/// The character that starts comments. It can never be a legend character.
+const COMMENT: char = ';';
+
+/// An error that prevents writing a level in [text format](self).
+#[derive(Debug)]
+pub enum WriteError {
+    /// The level contains more distinct blocks than there are legend characters.
+    TooManyBlocks {
+        /// The number of distinct blocks that could be assigned a character.
+        limit: usize,
+    },
+
+    /// A metadata property that must fit on a single line contains a line break.
+    MultilineProperty {
+        /// The keyword of the property, e.g. `"name"`.
+        keyword: &'static str,
+    },
+
+    /// A metadata property, or a line of the description, starts or ends with whitespace, which
+    /// parsing would strip.
+    PaddedProperty {
+        /// The keyword of the property, e.g. `"name"`.
+        keyword: &'static str,
+    },
+}
+
+impl std::fmt::Display for WriteError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::TooManyBlocks { limit } => {
+                write!(f, "level contains more than {limit} distinct blocks")
+            }
+            Self::MultilineProperty { keyword } => {
+                write!(f, "level {keyword} does not fit on a single line")
+            }
+            Self::PaddedProperty { keyword } => {
+                write!(f, "level {keyword} starts or ends with whitespace")
+            }
+        }
+    }
+}
+
+impl std::error::Error for WriteError {}
+
+/// A single meaningful line of input with its 1-based line number.
+struct Line<'a> {
+    number: usize,
+    text: &'a str,
+}
+
+impl<'a> Line<'a> {
+    /// Create a [`LoadError::Syntax`] pointing at 1-based character _column_ of this line.
+    fn error(&self, column: usize, reason: impl Into<String>) -> LoadError {
+        LoadError::Syntax {
+            line: self.number,
+            column,
+            reason: reason.into(),
+        }
+    }
+
+    /// Split this line into whitespace-separated tokens, each with its 1-based character column.
+    fn tokens(&self) -> Vec<(usize, &'a str)> {
+        let mut result = Vec::new();
+        let mut start = None;
+
+        for (column, (offset, c)) in self.text.char_indices().enumerate() {
+            match (start, c.is_whitespace()) {
+                (None, false) => start = Some((column + 1, offset)),
+                (Some((token_column, token_offset)), true) => {
+                    result.push((token_column, &self.text[token_offset..offset]));
+                    start = None;
+                }
+                _ => {}
+            }
+        }
+
+        if let Some((token_column, token_offset)) = start {
+            result.push((token_column, &self.text[token_offset..]));
+        }
+
+        result
+    }
+
+    /// Parse this line as `<keyword> <value>...` with exactly _N_ values.
+    fn values<T: std::str::FromStr, const N: usize>(&self) -> Result<[T; N], LoadError> {
+        let tokens = self.tokens();
+        if tokens.len() != N + 1 {
+            let column = tokens
+                .get(N + 1)
+                .map_or(self.text.chars().count() + 1, |t| t.0);
+            return Err(self.error(
+                column,
+                format!("expected {N} values after {:?}", tokens[0].1),
+            ));
+        }
+
+        let mut values = Vec::with_capacity(N);
+        for (column, token) in &tokens[1..] {
+            values.push(
+                token
+                    .parse()
+                    .map_err(|_| self.error(*column, format!("invalid number {token:?}")))?,
+            );
+        }
+
+        Ok(values.try_into().ok().unwrap())
+    }
+
+    /// Parse this line as `<keyword> <value>...` with exactly _N_ finite numbers.
+    fn finite_values<const N: usize>(&self) -> Result<[Float; N], LoadError> {
+        let values = self.values::<Float, N>()?;
+        if let Some(index) = values.iter().position(|value| !value.is_finite()) {
+            let (column, token) = self.tokens()[index + 1];
+            return Err(self.error(column, format!("number {token:?} is not finite")));
+        }
+        Ok(values)
+    }
+
+    /// Get the text after the keyword of this line without surrounding whitespace.
+    fn rest(&self) -> &'a str {
+        let text = self.text.trim();
+        text.split_once(char::is_whitespace)
+            .map_or("", |(_, rest)| rest.trim_start())
+    }
+
+    /// Parse this line as `<keyword> <seconds>`, see [`parse_seconds`].
+    fn seconds(&self) -> Result<Duration, LoadError> {
+        let [(_, keyword), (column, value)] = self.tokens()[..] else {
+            return Err(self.error(
+                1,
+                format!("expected <seconds> after {:?}", self.tokens()[0].1),
+            ));
+        };
+        parse_seconds(value)
+            .ok_or_else(|| self.error(column, format!("invalid time {value:?} for {keyword:?}")))
+    }
+}
+
+/// Parse a non-negative decimal number of seconds with at most nine fractional digits, e.g.
+/// `12.5`, exactly.
+fn parse_seconds(text: &str) -> Option<Duration> {
+    let (secs, fraction) = text.split_once('.').unwrap_or((text, ""));
+    let is_digits = |s: &str| s.chars().all(|c| c.is_ascii_digit());
+    if secs.is_empty() || !is_digits(secs) || !is_digits(fraction) || fraction.len() > 9 {
+        return None;
+    }
+
+    let nanos = if fraction.is_empty() {
+        0
+    } else {
+        fraction.parse::<u32>().ok()? * 10u32.pow(9 - fraction.len() as u32)
+    };
+    Some(Duration::new(secs.parse().ok()?, nanos))
+}
+
+/// Format _duration_ as a decimal number of seconds that [`parse_seconds`] reads back exactly.
+fn format_seconds(duration: Duration) -> String {
+    let fraction = format!("{:09}", duration.subsec_nanos());
+    let fraction = fraction.trim_end_matches('0');
+    if fraction.is_empty() {
+        duration.as_secs().to_string()
+    } else {
+        format!("{}.{fraction}", duration.as_secs())
+    }
+}
+
+/// Iterator over meaningful lines of input, skipping blank lines and comments.
+struct Lines<'a> {
+    inner: std::iter::Peekable<std::iter::Enumerate<std::str::Lines<'a>>>,
+
+    /// Number of lines in the input, used to report unexpected end of input.
+    total: usize,
+}
+
+impl<'a> Lines<'a> {
+    fn new(input: &'a str) -> Self {
+        Self {
+            inner: input.lines().enumerate().peekable(),
+            total: input.lines().count(),
+        }
+    }
+
+    /// Skip blank lines and comments.
+    fn skip_ignored(&mut self) {
+        while let Some((_, text)) = self.inner.peek() {
+            let trimmed = text.trim_start();
+            if trimmed.is_empty() || trimmed.starts_with(COMMENT) {
+                self.inner.next();
+            } else {
+                break;
+            }
+        }
+    }
+
+    /// Look at the first token of the next meaningful line without consuming it.
+    fn peek_keyword(&mut self) -> Option<&'a str> {
+        self.skip_ignored();
+        self.inner
+            .peek()
+            .and_then(|(_, text)| text.split_whitespace().next())
+    }
+
+    /// Get the next meaningful line, or fail with an error describing what was _expected_.
+    fn next(&mut self, expected: &str) -> Result<Line<'a>, LoadError> {
+        self.skip_ignored();
+        match self.inner.next() {
+            Some((index, text)) => Ok(Line {
+                number: index + 1,
+                text,
+            }),
+            None => Err(LoadError::Syntax {
+                line: self.total + 1,
+                column: 1,
+                reason: format!("unexpected end of input, expected {expected}"),
+            }),
+        }
+    }
+}
+
+impl Level {
+    /// Parse a level in [text format](self).
+    ///
+    /// Errors other than [`LoadError::Syntax`] are never returned.
+    pub fn from_text(input: &str) -> Result<Self, LoadError> {
+        let mut lines = Lines::new(input);
+
+        let header = lines.next("header")?;
+        match header.tokens()[..] {
+            [(_, HEADER), (column, version)] => match version.parse() {
+                Ok(VERSION) => {}
+                _ => {
+                    return Err(header.error(column, format!("unsupported version {version:?}")));
+                }
+            },
+            _ => return Err(header.error(1, format!("expected \"{HEADER} {VERSION}\""))),
+        }
+
+        let mut size = None;
+        let mut position = Vec3::ZERO;
+        let mut rotation = None;
+        let mut description: Vec<&str> = Vec::new();
+        let mut description_length = 0;
+        let mut metadata = Metadata {
+            format_version: VERSION,
+            ..Metadata::default()
+        };
+
+        loop {
+            let line = lines.next("\"legend\"")?;
+            match line.tokens()[0].1 {
+                "size" => size = Some((UVec3::from_array(line.values()?), line.number)),
+                "position" => position = Vec3::from_array(line.finite_values()?),
+                "rotation" => {
+                    let [x, y, z, w] = line.finite_values()?;
+                    let value = math::normalize(Quat::from_xyzw(x, y, z, w))
+                        .ok_or_else(|| line.error(1, "rotation must be a non-zero quaternion"))?;
+                    if rotation.replace(value).is_some() {
+                        return Err(line.error(1, "rotation is specified more than once"));
+                    }
+                }
+                keyword @ ("name" | "author") => {
+                    let value = line.rest();
+                    if value.len() > MAX_METADATA_LENGTH {
+                        return Err(line.error(
+                            1,
+                            format!("{keyword} is longer than {MAX_METADATA_LENGTH} bytes"),
+                        ));
+                    }
+                    match keyword {
+                        "name" => metadata.name = value.to_string(),
+                        _ => metadata.author = value.to_string(),
+                    }
+                }
+                "description" => {
+                    let value = line.rest();
+                    // Lines are joined with line breaks
+                    description_length += value.len() + !description.is_empty() as usize;
+                    if description_length > MAX_METADATA_LENGTH {
+                        return Err(line.error(
+                            1,
+                            format!("description is longer than {MAX_METADATA_LENGTH} bytes"),
+                        ));
+                    }
+                    description.push(value);
+                }
+                "spawn" => {
+                    let [x, y, z, yaw] = line.finite_values()?;
+                    metadata.spawn = Some(Spawn {
+                        position: Vec3::new(x, y, z),
+                        yaw,
+                    });
+                }
+                "par" => metadata.par_time = Some(line.seconds()?),
+                keyword @ ("gold" | "silver" | "bronze") => {
+                    let index = Medal::NAMES.iter().position(|n| *n == keyword).unwrap();
+                    metadata.medal_times[index] = Some(line.seconds()?);
+                }
+                "legend" => break,
+                other => {
+                    return Err(
+                        line.error(line.tokens()[0].0, format!("unknown property {other:?}"))
+                    );
+                }
+            }
+        }
+
+        let Some((size, size_line)) = size else {
+            return Err(header.error(1, "property \"size\" is missing"));
+        };
+        let volume = size.as_u64vec3().element_product();
+        if volume > MAX_LEVEL_VOLUME {
+            return Err(LoadError::Syntax {
+                line: size_line,
+                column: 1,
+                reason: format!("level size {size} is too large"),
+            });
+        }
+
+        let mut legend = HashMap::new();
+        while lines.peek_keyword().is_some_and(|k| k != "layer") {
+            let line = lines.next("legend entry")?;
+            let [(char_column, symbol), (column, spec)] = line.tokens()[..] else {
+                return Err(line.error(1, "expected <character> <block>"));
+            };
+
+            let mut chars = symbol.chars();
+            let (Some(symbol), None) = (chars.next(), chars.next()) else {
+                return Err(line.error(char_column, "expected a single character"));
+            };
+            if symbol == COMMENT {
+                return Err(line.error(char_column, format!("{COMMENT:?} starts a comment")));
+            }
+
+            let block = state::parse(spec)
+                .map_err(|e| line.error(column + e.position, e.error.to_string()))?;
+            if legend.insert(symbol, block).is_some() {
+                return Err(line.error(char_column, format!("duplicate character {symbol:?}")));
+            }
+        }
+
+        // Buffers grow as rows are read so that a huge size cannot reserve memory for blocks that
+        // the input does not contain
+        let mut data = Vec::new();
+        for z in 0..size.z {
+            let line = lines.next(&format!("\"layer {z}\""))?;
+            match line.tokens()[..] {
+                [(_, "layer"), (column, index)] => {
+                    if index.parse() != Ok(z) {
+                        return Err(line.error(column, format!("expected layer {z}")));
+                    }
+                }
+                _ => return Err(line.error(1, format!("expected \"layer {z}\""))),
+            }
+
+            let mut layer = Vec::new();
+            for _ in 0..size.y {
+                let line = lines.next("layer row")?;
+                let mut row = Vec::new();
+                for (index, symbol) in line.text.trim_end().chars().enumerate() {
+                    if index as u32 >= size.x {
+                        return Err(line.error(index + 1, format!("row is longer than {}", size.x)));
+                    }
+
+                    let block = legend.get(&symbol).ok_or_else(|| {
+                        if symbol == COMMENT {
+                            line.error(index + 1, format!("{COMMENT:?} cannot be used in layers"))
+                        } else {
+                            line.error(index + 1, format!("character {symbol:?} is not in legend"))
+                        }
+                    })?;
+
+                    row.push(block.clone());
+                }
+
+                if row.len() as u32 != size.x {
+                    return Err(
+                        line.error(row.len() + 1, format!("row is shorter than {}", size.x))
+                    );
+                }
+                layer.push(row);
+            }
+
+            // First row has the greatest Y
+            data.extend(layer.into_iter().rev().flatten());
+        }
+
+        if lines.peek_keyword().is_some() {
+            let line = lines.next("end of input")?;
+            return Err(line.error(1, "expected end of input"));
+        }
+
+        metadata.description = description.join("\n");
+
+        let blocks = Array3::from_vec(size, data).unwrap();
+        let mut level = Self::from_blocks(blocks, position, rotation.unwrap_or(Quat::IDENTITY));
+        level.metadata = metadata;
+        Ok(level)
+    }
+
+    /// Write this level in [text format](self).
+    ///
+    /// Legend characters are chosen automatically. Once printable ASCII characters run out,
+    /// letters and digits of other scripts are used.
+    ///
+    /// Fails if the name or author of the level contains a line break, if the name, author or a
+    /// line of the description starts or ends with whitespace, or if the level contains more
+    /// distinct blocks than there are legend characters.
+    pub fn to_text(&self) -> Result<String, WriteError> {
+        let mut legend: Vec<(char, String)> = Vec::new();
+        let mut symbols = HashMap::new();
+        let mut used = HashSet::new();
+        let mut fallback = fallback_symbols();
+
+        for block in &self.blocks {
+            let description = block.to_string();
+            if symbols.contains_key(&description) {
+                continue;
+            }
+
+            let symbol = preferred_symbols(block.kind_name())
+                .find(|c| !used.contains(c))
+                .or_else(|| fallback.find(|c| !used.contains(c)))
+                .ok_or(WriteError::TooManyBlocks { limit: used.len() })?;
+            used.insert(symbol);
+            legend.push((symbol, description.clone()));
+            symbols.insert(description, symbol);
+        }
+
+        let shape = self.blocks.shape();
+        let mut result = String::new();
+
+        // Writing to a String never fails
+        let mut write = |args: std::fmt::Arguments| result.write_fmt(args).unwrap();
+
+        write(format_args!("{HEADER} {VERSION}\n"));
+        write(format_args!("size {} {} {}\n", shape.x, shape.y, shape.z));
+        write(format_args!(
+            "position {} {} {}\n",
+            self.position.x, self.position.y, self.position.z
+        ));
+        let rotation = self.rotation;
+        write(format_args!(
+            "rotation {} {} {} {}\n",
+            rotation.x, rotation.y, rotation.z, rotation.w
+        ));
+
+        let metadata = &self.metadata;
+        for (keyword, value) in [("name", &metadata.name), ("author", &metadata.author)] {
+            if value.contains(['\n', '\r']) {
+                return Err(WriteError::MultilineProperty { keyword });
+            }
+            if value.trim() != value {
+                return Err(WriteError::PaddedProperty { keyword });
+            }
+            if !value.is_empty() {
+                write(format_args!("{keyword} {value}\n"));
+            }
+        }
+        if !metadata.description.is_empty() {
+            for line in metadata.description.split('\n') {
+                if line.trim() != line {
+                    return Err(WriteError::PaddedProperty {
+                        keyword: "description",
+                    });
+                }
+                write(format_args!("description {line}\n"));
+            }
+        }
+        if let Some(spawn) = &metadata.spawn {
+            let position = spawn.position;
+            write(format_args!(
+                "spawn {} {} {} {}\n",
+                position.x, position.y, position.z, spawn.yaw
+            ));
+        }
+        let times = std::iter::once(("par", metadata.par_time)).chain(
+            Medal::ALL
+                .into_iter()
+                .map(|medal| (medal.name(), metadata.medal_time(medal))),
+        );
+        for (keyword, time) in times {
+            if let Some(time) = time {
+                write(format_args!("{keyword} {}\n", format_seconds(time)));
+            }
+        }
+
+        write(format_args!("\nlegend\n"));
+        for (symbol, description) in &legend {
+            write(format_args!("{symbol} {description}\n"));
+        }
+
+        for z in 0..shape.z {
+            write(format_args!("\nlayer {z}\n"));
+            for y in (0..shape.y).rev() {
+                let row: String = (0..shape.x)
+                    .map(|x| {
+                        let block = &self.blocks[UVec3::new(x, y, z)];
+                        symbols[&block.to_string()]
+                    })
+                    .collect();
+                write(format_args!("{row}\n"));
+            }
+        }
+
+        Ok(result)
+    }
+}
+
+/// Characters that should preferably represent blocks of kind _kind_ in a legend, in order of
+/// preference.
+fn preferred_symbols(kind: &str) -> impl Iterator<Item = char> {
+    let preferred = match kind {
+        "air" => Some('.'),
+        "stone" => Some('#'),
+        _ => None,
+    };
+
+    let initial = kind.chars().next().filter(char::is_ascii_alphabetic);
+
+    preferred
+        .into_iter()
+        .chain(initial)
+        .chain(initial.map(|c| c.to_ascii_uppercase()))
+}
+
+/// Characters that can represent blocks of any kind in a legend, in order of preference: printable
+/// ASCII characters, then letters and digits of other scripts.
+fn fallback_symbols() -> impl Iterator<Item = char> {
+    ('!'..='~')
+        .chain(('\u{a1}'..=char::MAX).filter(|c| c.is_alphanumeric()))
+        .filter(|c| *c != COMMENT)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::content::block::Block;
+
+    fn sample() -> Level {
+        let mut blocks = Array3::default(UVec3::new(3, 2, 2));
+        blocks[UVec3::new(1, 1, 1)] = state::parse("stone").unwrap();
+        blocks[UVec3::new(2, 0, 1)] =
+            state::parse("pusher[facing=north,contents=pusher[contents=sand]]").unwrap();
+
+        let rotation = math::rotation_z(-0.3);
+        let mut level = Level::from_blocks(blocks, Vec3::new(1.0, -2.5, 3.0), rotation);
+        level.metadata = Metadata {
+            name: "Sample".to_string(),
+            author: "Someone".to_string(),
+            description: "Line one\n\nLine three".to_string(),
+            spawn: Some(Spawn {
+                position: Vec3::new(0.5, 1.0, 1.5),
+                yaw: 0.25,
+            }),
+            par_time: Some(Duration::new(12, 500_000_000)),
+            medal_times: [
+                Some(Duration::from_secs(10)),
+                None,
+                Some(Duration::new(20, 1)),
+            ],
+            format_version: VERSION,
+        };
+        level
+    }
+
+    /// A level of a single air block with extra _properties_.
+    fn tiny(properties: &str) -> String {
+        format!("{HEADER} {VERSION}\nsize 1 1 1\n{properties}legend\n. air\nlayer 0\n.\n")
+    }
+
+    fn syntax_error_line(input: &str) -> usize {
+        match Level::from_text(input) {
+            Err(LoadError::Syntax { line, .. }) => line,
+            Err(error) => panic!("unexpected error {error}"),
+            Ok(_) => panic!("input was accepted:\n{input}"),
+        }
+    }
+
+    #[test]
+    fn round_trip_is_exact() {
+        let level = sample();
+        let text = level.to_text().unwrap();
+        let loaded = Level::from_text(&text).unwrap();
+
+        assert_eq!(loaded.to_text().unwrap(), text);
+        assert_eq!(loaded.metadata, level.metadata);
+        assert_eq!(loaded.position, level.position);
+        assert_eq!(loaded.rotation, level.rotation);
+        for (a, b) in level.blocks.into_iter().zip(&loaded.blocks) {
+            assert_eq!(a.to_string(), b.to_string());
+        }
+    }
+
+    #[test]
+    fn many_distinct_blocks_get_legend_characters() {
+        let mut states = state::all_states("relay").unwrap();
+        states.extend(state::all_states("wire").unwrap());
+        let mut blocks = Array3::default(UVec3::new(states.len() as u32, 1, 1));
+        for (x, block) in states.into_iter().enumerate() {
+            blocks[UVec3::new(x as u32, 0, 0)] = block;
+        }
+        let level = Level::from_blocks(blocks, Vec3::ZERO, Quat::IDENTITY);
+
+        let text = level.to_text().unwrap();
+        let loaded = Level::from_text(&text).unwrap();
+        for (a, b) in level.blocks.into_iter().zip(&loaded.blocks) {
+            assert_eq!(a.to_string(), b.to_string());
+        }
+    }
+
+    #[test]
+    fn invalid_input_is_rejected_at_its_line() {
+        assert!(Level::from_text(&tiny("")).is_ok());
+
+        assert_eq!(syntax_error_line("trapiron-level 2\n"), 1);
+        assert_eq!(syntax_error_line(&tiny("size 1 1\n")), 3);
+        assert_eq!(syntax_error_line(&tiny("position 0 inf 0\n")), 3);
+        assert_eq!(syntax_error_line(&tiny("rotation 0 0 0 0\n")), 3);
+        assert_eq!(syntax_error_line(&tiny("spawn 0 0 NaN 0\n")), 3);
+        assert_eq!(syntax_error_line(&tiny("par -1\n")), 3);
+        assert_eq!(syntax_error_line(&tiny("gold 1.0000000001\n")), 3);
+        assert_eq!(syntax_error_line(&tiny("colour red\n")), 3);
+
+        let long = "x".repeat(MAX_METADATA_LENGTH + 1);
+        assert_eq!(syntax_error_line(&tiny(&format!("name {long}\n"))), 3);
+        assert!(Level::from_text(&tiny(&format!("name {}\n", &long[1..]))).is_ok());
+
+        let text = tiny("");
+        assert_eq!(syntax_error_line(&text.replace(". air", ". wood")), 4);
+        assert_eq!(syntax_error_line(&text.replace("\n.\n", "\nx\n")), 6);
+        assert_eq!(syntax_error_line(&text.replace("\n.\n", "\n..\n")), 6);
+        assert_eq!(syntax_error_line(&text.replace("layer 0", "layer 1")), 5);
+        assert_eq!(syntax_error_line(&format!("{text}.\n")), 7);
+        assert_eq!(
+            syntax_error_line(&text.replace("size 1 1 1", "size 4096 4096 2")),
+            2
+        );
+    }
+
+    #[test]
+    fn unrepresentable_metadata_is_not_written() {
+        let mut level = Level::from_blocks(
+            Array3::fill(UVec3::ONE, &Block::default()),
+            Vec3::ZERO,
+            Quat::IDENTITY,
+        );
+
+        level.metadata.name = "Two\nlines".to_string();
+        let result = level.to_text();
+        assert!(matches!(
+            result,
+            Err(WriteError::MultilineProperty { keyword: "name" })
+        ));
+
+        level.metadata.name = " Padded".to_string();
+        let result = level.to_text();
+        assert!(matches!(
+            result,
+            Err(WriteError::PaddedProperty { keyword: "name" })
+        ));
+
+        level.metadata.name.clear();
+        level.metadata.description = "Fine\nIndented ".to_string();
+        let result = level.to_text();
+        assert!(matches!(
+            result,
+            Err(WriteError::PaddedProperty {
+                keyword: "description"
+            })
+        ));
+    }
+}
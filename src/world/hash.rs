@@ -0,0 +1,187 @@
+//! Deterministic hashing of simulation state.
+//!
+//! Hashes computed here only depend on the values of the state, never on memory layout, pointer
+//! values, platform endianness or the Rust version, so they can be stored in files and compared
+//! across machines. This makes them suitable for detecting desyncs in
+//! [replays](crate::world::replay).
+//!
+//! [`std::hash::Hash`] is not used because it makes no such guarantees.
+
+use crate::{
+    content::block::{Block, Nested},
+    world::{Float, Level, Vec2, Vec3, World, YawPitch, run, update},
+};
+
+/// A 64-bit FNV-1a hasher for [`StateHash`] values.
+pub struct StateHasher(u64);
+
+impl StateHasher {
+    const OFFSET_BASIS: u64 = 0xcbf2_9ce4_8422_2325;
+    const PRIME: u64 = 0x0000_0100_0000_01b3;
+
+    /// Create a hasher with no data written to it.
+    pub fn new() -> Self {
+        Self(Self::OFFSET_BASIS)
+    }
+
+    /// Get the hash of all data written so far.
+    pub fn finish(&self) -> u64 {
+        self.0
+    }
+
+    pub fn write_bytes(&mut self, bytes: &[u8]) {
+        for byte in bytes {
+            self.0 ^= *byte as u64;
+            self.0 = self.0.wrapping_mul(Self::PRIME);
+        }
+    }
+
+    pub fn write_u8(&mut self, value: u8) {
+        self.write_bytes(&[value]);
+    }
+
+    pub fn write_u32(&mut self, value: u32) {
+        self.write_bytes(&value.to_le_bytes());
+    }
+
+    pub fn write_u64(&mut self, value: u64) {
+        self.write_bytes(&value.to_le_bytes());
+    }
+
+    /// Write a length or count.
+    pub fn write_len(&mut self, value: usize) {
+        self.write_u64(value as u64);
+    }
+
+    /// Write a float bit-exactly, so that e.g. `0.0` and `-0.0` hash differently.
+    pub fn write_float(&mut self, value: Float) {
+        self.write_u32(value.to_bits());
+    }
+
+    /// Write a string, prefixed with its length so that concatenations are not ambiguous.
+    pub fn write_str(&mut self, value: &str) {
+        self.write_len(value.len());
+        self.write_bytes(value.as_bytes());
+    }
+}
+
+impl Default for StateHasher {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// A value that can be hashed deterministically with a [`StateHasher`].
+pub trait StateHash {
+    /// Feed the complete state of `self` into _hasher_.
+    fn state_hash(&self, hasher: &mut StateHasher);
+
+    /// Compute the hash of `self` alone.
+    fn checksum(&self) -> u64 {
+        let mut hasher = StateHasher::new();
+        self.state_hash(&mut hasher);
+        hasher.finish()
+    }
+}
+
+impl StateHash for Float {
+    fn state_hash(&self, hasher: &mut StateHasher) {
+        hasher.write_float(*self);
+    }
+}
+
+impl StateHash for Vec2 {
+    fn state_hash(&self, hasher: &mut StateHasher) {
+        for coord in self.to_array() {
+            hasher.write_float(coord);
+        }
+    }
+}
+
+impl StateHash for Vec3 {
+    fn state_hash(&self, hasher: &mut StateHasher) {
+        for coord in self.to_array() {
+            hasher.write_float(coord);
+        }
+    }
+}
+
+impl StateHash for YawPitch {
+    fn state_hash(&self, hasher: &mut StateHasher) {
+        hasher.write_float(self.yaw);
+        hasher.write_float(self.pitch);
+    }
+}
+
+impl StateHash for Nested {
+    fn state_hash(&self, hasher: &mut StateHasher) {
+        hasher.write_str(&self.kind);
+        hasher.write_u32(self.data.state);
+        hasher.write_len(self.data.nested.len());
+        for nested in &self.data.nested {
+            nested.state_hash(hasher);
+        }
+    }
+}
+
+impl StateHash for Block {
+    fn state_hash(&self, hasher: &mut StateHasher) {
+        self.serialize_nested().state_hash(hasher);
+    }
+}
+
+impl StateHash for Level {
+    fn state_hash(&self, hasher: &mut StateHasher) {
+        for dim in self.blocks.shape().to_array() {
+            hasher.write_u32(dim);
+        }
+        for block in &self.blocks {
+            block.state_hash(hasher);
+        }
+        self.position.state_hash(hasher);
+        for component in self.rotation.to_array() {
+            hasher.write_float(component);
+        }
+        self.updates.state_hash(hasher);
+    }
+}
+
+impl StateHash for update::Queue {
+    fn state_hash(&self, hasher: &mut StateHasher) {
+        hasher.write_u64(self.tick());
+        hasher.write_len(self.iter().count());
+        for (due, update) in self.iter() {
+            hasher.write_u64(due);
+            for coord in update.pos.to_array() {
+                hasher.write_u32(coord as u32);
+            }
+            hasher.write_u8(update.cause.code());
+        }
+    }
+}
+
+impl StateHash for World {
+    fn state_hash(&self, hasher: &mut StateHasher) {
+        hasher.write_len(self.levels.len());
+        for level in &self.levels {
+            level.state_hash(hasher);
+        }
+        self.player.state_hash(hasher);
+        self.run.state_hash(hasher);
+        self.checkpoint.position.state_hash(hasher);
+        self.checkpoint.rotation.state_hash(hasher);
+        self.spawn.position.state_hash(hasher);
+        self.spawn.rotation.state_hash(hasher);
+    }
+}
+
+impl StateHash for run::Run {
+    fn state_hash(&self, hasher: &mut StateHasher) {
+        hasher.write_u8(self.state as u8);
+        hasher.write_u64(self.time.as_secs());
+        hasher.write_u32(self.time.subsec_nanos());
+        hasher.write_u64(self.logic_ticks);
+        hasher.write_u32(self.respawns);
+        hasher.write_u8(self.practice as u8);
+    }
+}
@@ -0,0 +1,263 @@
+//! Bit-deterministic math functions for simulation.
+//!
+//! The results of transcendental functions in `std` such as [`f32::powf`] or [`f32::sin`] depend
+//! on the platform's math library and may differ in the last bits between platforms or even builds.
+//! Since simulation must produce bit-identical results for identical event streams, it must use the
+//! functions in this module instead.
+//!
+//! Functions here only use IEEE 754 operations that are required to be correctly rounded: addition,
+//! subtraction, multiplication, division, square root and rounding to integers. Rust never fuses
+//! multiplication and addition implicitly, so these functions behave identically on every
+//! platform.
+//!
+//! Accuracy is within a few ULPs of the exact result, which is plenty for game physics.
+
+use crate::world::{Float, Quat, Vec3};
+
+/// Compute sine and cosine of _x_ (in radians) at the same time.
+///
+/// Accurate for `|x|` up to about 10⁵; larger arguments lose precision, but the result remains
+/// deterministic.
+pub fn sin_cos(x: Float) -> (Float, Float) {
+    // pi/2 split into three parts (Cody-Waite reduction). The first two have 8 significant bits,
+    // so their products with quadrant numbers below 2^16, i.e. for |x| up to about 10^5, are
+    // exact, and so is subtracting the first product from x. The product with the tiny third part
+    // and the remaining subtractions are rounded
+    const FRAC_PI_2_A: Float = 201.0 / 128.0;
+    const FRAC_PI_2_B: Float = 253.0 / 524_288.0;
+    const FRAC_PI_2_C: Float = 1.267_590_8e-6;
+
+    let quadrant = (x * std::f32::consts::FRAC_2_PI).round();
+    let r = ((x - quadrant * FRAC_PI_2_A) - quadrant * FRAC_PI_2_B) - quadrant * FRAC_PI_2_C;
+
+    // Taylor series on [-pi/4, pi/4]
+    let r2 = r * r;
+    let sin = r + r
+        * r2
+        * (-1.0 / 6.0 + r2 * (1.0 / 120.0 + r2 * (-1.0 / 5040.0 + r2 * (1.0 / 362_880.0))));
+    let cos = 1.0
+        + r2 * (-0.5
+            + r2 * (1.0 / 24.0
+                + r2 * (-1.0 / 720.0 + r2 * (1.0 / 40_320.0 + r2 * (-1.0 / 3_628_800.0)))));
+
+    match (quadrant as i64).rem_euclid(4) {
+        0 => (sin, cos),
+        1 => (cos, -sin),
+        2 => (-sin, -cos),
+        _ => (-cos, sin),
+    }
+}
+
+/// Compute 2 raised to the power of _x_.
+///
+/// Returns zero for _x_ below -126 and infinity for _x_ above 128.
+pub fn exp2(x: Float) -> Float {
+    if x.is_nan() {
+        return x;
+    }
+    if x < -126.0 {
+        return 0.0;
+    }
+    if x > 128.0 {
+        return Float::INFINITY;
+    }
+
+    // x = n + f, where n is an integer and |f| <= 0.5
+    let n = x.round();
+    let f = x - n;
+
+    // Taylor series of e^(f ln 2)
+    const C: [Float; 8] = [
+        1.0,
+        std::f32::consts::LN_2,
+        0.240_226_5,
+        0.055_504_11,
+        0.009_618_129,
+        0.001_333_355_8,
+        0.000_154_035_3,
+        0.000_015_252_734,
+    ];
+    let mantissa = C[0]
+        + f * (C[1] + f * (C[2] + f * (C[3] + f * (C[4] + f * (C[5] + f * (C[6] + f * C[7]))))));
+
+    // 2^n, split in two factors so that n = 128 and n = -126 with a mantissa below 1 work
+    let half = n as i32 / 2;
+    let scale = |exponent: i32| Float::from_bits(((exponent + 127) as u32) << 23);
+    mantissa * scale(half) * scale(n as i32 - half)
+}
+
+/// Rotate _v_ around the Z axis by _angle_ (in radians), counterclockwise when viewed from above.
+///
+/// This is equivalent to `Mat3::from_rotation_z(angle) * v`.
+pub fn rotate_z(v: Vec3, angle: Float) -> Vec3 {
+    let (sin, cos) = sin_cos(angle);
+    Vec3::new(cos * v.x - sin * v.y, sin * v.x + cos * v.y, v.z)
+}
+
+/// Compute the angle (in radians) between the positive X axis and the point (_x_, _y_), between
+/// -π and π.
+///
+/// This is equivalent to `y.atan2(x)`.
+pub fn atan2(y: Float, x: Float) -> Float {
+    use std::f32::consts::{FRAC_PI_2, FRAC_PI_6, PI};
+
+    if x.is_nan() || y.is_nan() {
+        return Float::NAN;
+    }
+
+    // Reduce to the angle of a ratio t between 0 and 1
+    let (ax, ay) = (x.abs(), y.abs());
+    let swapped = ay > ax;
+    let (num, den) = if swapped { (ax, ay) } else { (ay, ax) };
+    let t = if den == 0.0 {
+        0.0
+    } else if num.is_infinite() {
+        1.0
+    } else {
+        num / den
+    };
+
+    // atan(t) = pi/6 + atan((t * sqrt(3) - 1) / (t + sqrt(3))) brings t below tan(pi/12)
+    const SQRT_3: Float = 1.732_050_8;
+    const TAN_FRAC_PI_12: Float = 0.267_949_2;
+    let (base, r) = if t > TAN_FRAC_PI_12 {
+        (FRAC_PI_6, (t * SQRT_3 - 1.0) / (t + SQRT_3))
+    } else {
+        (0.0, t)
+    };
+
+    // Taylor series on [-tan(pi/12), tan(pi/12)]
+    let r2 = r * r;
+    let series = r - r
+        * r2
+        * (1.0 / 3.0 - r2 * (1.0 / 5.0 - r2 * (1.0 / 7.0 - r2 * (1.0 / 9.0 - r2 * (1.0 / 11.0)))));
+
+    let mut angle = base + series;
+    if swapped {
+        angle = FRAC_PI_2 - angle;
+    }
+    if x.is_sign_negative() {
+        angle = PI - angle;
+    }
+    if y.is_sign_negative() { -angle } else { angle }
+}
+
+/// Get the rotation around the Z axis by _angle_ (in radians), counterclockwise when viewed from
+/// above.
+///
+/// This is equivalent to `Quat::from_rotation_z(angle)`.
+pub fn rotation_z(angle: Float) -> Quat {
+    let (sin, cos) = sin_cos(angle * 0.5);
+    Quat::from_xyzw(0.0, 0.0, sin, cos)
+}
+
+/// Scale quaternion _q_ to unit length, or return `None` if it is zero or not finite.
+///
+/// Quaternions that are of unit length up to rounding errors are returned unchanged, so
+/// normalizing a normalized quaternion again never changes it.
+pub fn normalize(q: Quat) -> Option<Quat> {
+    let length_squared = ((q.x * q.x + q.y * q.y) + q.z * q.z) + q.w * q.w;
+    if !length_squared.is_finite() || length_squared == 0.0 {
+        return None;
+    }
+    if (length_squared - 1.0).abs() <= 4.0 * Float::EPSILON {
+        return Some(q);
+    }
+
+    let length = length_squared.sqrt();
+    Some(Quat::from_xyzw(
+        q.x / length,
+        q.y / length,
+        q.z / length,
+        q.w / length,
+    ))
+}
+
+/// Rotate _v_ by unit quaternion _q_.
+///
+/// This is equivalent to `q * v`.
+pub fn rotate(q: Quat, v: Vec3) -> Vec3 {
+    let u = Vec3::new(q.x, q.y, q.z);
+    let t = u.cross(v) * 2.0;
+    v + t * q.w + u.cross(t)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Results are pinned bit-exactly: simulation depends on them, so they must be identical on
+    // every platform and only change deliberately, invalidating recorded replays.
+
+    #[test]
+    fn sin_cos_is_pinned() {
+        const CASES: [(Float, u32, u32); 9] = [
+            (0.0, 0x0000_0000, 0x3f80_0000),
+            (0.5, 0x3ef5_7744, 0x3f60_a940),
+            (-1.0, 0xbf57_6aa4, 0x3f0a_5140),
+            (2.0, 0x3f68_c7b7, 0xbed5_1132),
+            (3.0, 0x3e10_81c3, 0xbf7d_7026),
+            (-100.25, 0x3e8d_f805, 0x3f75_f639),
+            (12345.678, 0xbf34_4b08, 0x3f35_be20),
+            (65000.0, 0x3edd_c750, 0x3f66_bcad),
+            (-99999.9, 0xbe0c_2c4e, 0xbf7d_9715),
+        ];
+
+        for (x, sin, cos) in CASES {
+            let (actual_sin, actual_cos) = sin_cos(x);
+            assert_eq!(
+                (actual_sin.to_bits(), actual_cos.to_bits()),
+                (sin, cos),
+                "sin_cos({x})"
+            );
+        }
+    }
+
+    #[test]
+    fn exp2_is_pinned() {
+        const CASES: [(Float, u32); 8] = [
+            (0.0, 0x3f80_0000),
+            (1.0, 0x4000_0000),
+            (-0.5, 0x3f35_04f3),
+            (0.3, 0x3f9d_9624),
+            (-0.032, 0x3f7a_625e),
+            (10.75, 0x44d7_44fd),
+            (-126.0, 0x0080_0000),
+            (128.0, 0x7f80_0000),
+        ];
+
+        for (x, expected) in CASES {
+            assert_eq!(exp2(x).to_bits(), expected, "exp2({x})");
+        }
+    }
+
+    #[test]
+    fn rotate_z_is_pinned() {
+        const CASES: [([Float; 3], Float, [u32; 3]); 3] = [
+            (
+                [1.0, 0.0, 0.0],
+                0.3,
+                [0x3f74_90ef, 0x3e97_4e6d, 0x0000_0000],
+            ),
+            (
+                [5.0, -2.5, 1.0],
+                -1.2,
+                [0xbf04_afe6, 0xc0b2_1d68, 0x3f80_0000],
+            ),
+            (
+                [0.25, 4.0, -3.0],
+                7.0,
+                [0xc01c_204a, 0x404b_82c1, 0xc040_0000],
+            ),
+        ];
+
+        for (v, angle, expected) in CASES {
+            let actual = rotate_z(Vec3::from_array(v), angle);
+            assert_eq!(
+                actual.to_array().map(Float::to_bits),
+                expected,
+                "rotate_z({v:?}, {angle})"
+            );
+        }
+    }
+}
@@ -0,0 +1,162 @@
+//! Information about a level that does not change during play: its name, authoring information,
+//! spawn point and target times.
+//!
+//! [`Metadata`] is stored in level files next to the blocks, see [`format`](crate::world::format).
+//! The spawn point of the first level of a [world](World) that declares one is where the player
+//! character starts. Levels without a spawn point start the character on their first [start
+//! block](crate::content::block::Start) instead.
+
+use std::time::Duration;
+
+use crate::{
+    content::block::Block,
+    world::{Float, Level, Vec3, World, YawPitch, math, respawn::Checkpoint},
+};
+
+/// One of the awards for finishing a [run](crate::world::run) within a time set by the level
+/// author.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Medal {
+    /// The best medal.
+    Gold,
+    /// The medal between gold and bronze.
+    Silver,
+    /// The medal that is easiest to earn.
+    Bronze,
+}
+
+impl Medal {
+    /// All medals from best to worst, in the order of their indices.
+    pub const ALL: [Medal; 3] = [Self::Gold, Self::Silver, Self::Bronze];
+
+    /// Lowercase names of all medals, in the order of their indices.
+    pub const NAMES: [&'static str; 3] = ["gold", "silver", "bronze"];
+
+    /// Get the index of this medal in [`Self::ALL`].
+    pub fn index(self) -> usize {
+        self as usize
+    }
+
+    /// Get the lowercase name of this medal, e.g. `"gold"`.
+    pub fn name(self) -> &'static str {
+        Self::NAMES[self.index()]
+    }
+}
+
+/// The place where the player character starts in a level.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct Spawn {
+    /// Position of the character (center of feet) in level coordinates.
+    pub position: Vec3,
+
+    /// The yaw the character faces, relative to the level coordinate frame.
+    pub yaw: Float,
+}
+
+/// Descriptive information and authoring settings of a level.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Metadata {
+    /// The name of the level shown to players. Must fit on a single line.
+    pub name: String,
+
+    /// The name of the author of the level. Must fit on a single line.
+    pub author: String,
+
+    /// A description of the level, possibly spanning several lines.
+    pub description: String,
+
+    /// Where the player character starts, or `None` to start on a start block.
+    pub spawn: Option<Spawn>,
+
+    /// The time the level author considers a good run, if any.
+    pub par_time: Option<Duration>,
+
+    /// The slowest run time that earns each [medal](Medal), indexed by [`Medal::index`]; `None` if
+    /// the level does not award the medal.
+    pub medal_times: [Option<Duration>; 3],
+
+    /// The version of the file format the level was read from, either
+    /// [binary](crate::world::format::binary) or [text](crate::world::format::text), or `0` if it
+    /// was not read from a file.
+    ///
+    /// Levels are always written in the current version of a format.
+    pub format_version: u16,
+}
+
+impl Metadata {
+    /// Get the slowest run time that earns _medal_, or `None` if the level does not award it.
+    pub fn medal_time(&self, medal: Medal) -> Option<Duration> {
+        self.medal_times[medal.index()]
+    }
+
+    /// Get the best medal earned by a run finished in _time_, if any.
+    pub fn medal(&self, time: Duration) -> Option<Medal> {
+        Medal::ALL
+            .into_iter()
+            .find(|medal| self.medal_time(*medal).is_some_and(|limit| time <= limit))
+    }
+}
+
+impl Level {
+    /// Get the spawn point declared in the [metadata](Metadata::spawn) of this level in world
+    /// coordinates, or `None` if there is none.
+    ///
+    /// The character faces the horizontal part of the spawn direction in the world, level with the
+    /// horizon.
+    pub fn spawn_point(&self) -> Option<Checkpoint> {
+        let spawn = self.metadata.spawn?;
+        let facing = YawPitch {
+            yaw: spawn.yaw,
+            pitch: 0.0,
+        };
+        let direction = self.vector_to_world(facing.direction());
+        let yaw = if direction.x == 0.0 && direction.y == 0.0 {
+            0.0
+        } else {
+            math::atan2(-direction.y, direction.x)
+        };
+
+        Some(Checkpoint {
+            position: self.to_world(spawn.position),
+            rotation: YawPitch { yaw, pitch: 0.0 },
+        })
+    }
+}
+
+impl World {
+    /// Find the point where the player character starts in _levels_: the first declared [spawn
+    /// point](Level::spawn_point), otherwise the top of the first [start
+    /// block](crate::content::block::Start), otherwise above the center of the first level.
+    pub(crate) fn find_spawn(levels: &[Level]) -> Checkpoint {
+        if let Some(spawn) = levels.iter().find_map(Level::spawn_point) {
+            return spawn;
+        }
+
+        let start = levels.iter().find_map(|level| {
+            let (pos, _) = level
+                .blocks
+                .pos_iter()
+                .find(|(_, block)| matches!(block, Block::Start(_)))?;
+            Some(level.top_of(pos.as_vec3()))
+        });
+
+        // Blocks span from -0.5 to shape - 0.5, so this is half a block beyond the top face
+        let position = start.or_else(|| {
+            let level = levels.first()?;
+            let shape = level.blocks.shape().as_vec3();
+            let up = level.up();
+            let mut center = shape / 2.0;
+            center[up.axis()] = if up.offset().max_element() > 0 {
+                shape[up.axis()]
+            } else {
+                -1.0
+            };
+            Some(level.to_world(center))
+        });
+
+        Checkpoint {
+            position: position.unwrap_or_default(),
+            rotation: YawPitch::default(),
+        }
+    }
+}
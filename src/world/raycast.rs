@@ -0,0 +1,203 @@
+//! Finding the blocks that rays, such as the line of sight of the player character, run into.
+//!
+//! Rays are traced through the block grid of each level in its own coordinate frame with a
+//! [`GridRayIter`], so they honor the position and rotation of every level. What counts as being
+//! hit depends on the [`Target`] of the raycast: gameplay interaction and block picking want every
+//! block including ones that bodies can pass through, such as levers, whereas cameras only care
+//! about the exact collision shapes of blocks. Air is never hit.
+
+use crate::{
+    content::block::Block,
+    world::{Direction, Float, IVec3, Level, Vec3, World, vec_iter::GridRayIter},
+};
+
+/// The kind of obstacles that stop a ray.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Target {
+    /// Any block other than air, hit where the ray enters its cell. Suits interaction and block
+    /// picking.
+    Cell,
+
+    /// The [collision shape](crate::content::block::Physics::shape) of blocks, hit exactly. Suits
+    /// cameras and line of sight checks.
+    Shape,
+}
+
+/// A block that a ray has hit.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Hit {
+    /// Position of the block in level coordinates.
+    pub pos: IVec3,
+
+    /// The face of the block, or of the part of its shape, that the ray hit, in level
+    /// coordinates; `None` if the ray starts inside it.
+    ///
+    /// The cell in front of the face is at `pos + face.offset()`.
+    pub face: Option<Direction>,
+
+    /// Distance from the origin of the ray to the point where it hit the block.
+    pub distance: Float,
+}
+
+impl Level {
+    /// Find the first block of this level that a ray from _origin_ along _direction_ hits within
+    /// _max_distance_, according to _target_.
+    ///
+    /// _origin_ and _direction_ are in world coordinates; _direction_ must be a unit vector.
+    pub fn raycast(
+        &self,
+        origin: Vec3,
+        direction: Vec3,
+        max_distance: Float,
+        target: Target,
+    ) -> Option<Hit> {
+        let origin = self.to_local(origin);
+        let direction = self.vector_to_local(direction);
+
+        let steps = GridRayIter::new(origin, direction).take_while(|s| s.distance <= max_distance);
+        for step in steps {
+            let block = match self.block(step.cell) {
+                None | Some(Block::Air(_)) => continue,
+                Some(block) => block,
+            };
+
+            let hit = match target {
+                Target::Cell => Some(Hit {
+                    pos: step.cell,
+                    face: step.face,
+                    distance: step.distance,
+                }),
+                Target::Shape => {
+                    // Parts lie within the cell, so no other cell can have a closer hit
+                    let center = step.cell.as_vec3();
+                    block
+                        .physics()
+                        .shape
+                        .iter()
+                        .filter_map(|part| part.intersect_ray(origin - center, direction))
+                        .min_by(|a, b| a.0.total_cmp(&b.0))
+                        .filter(|(distance, _)| *distance <= max_distance)
+                        .map(|(distance, face)| Hit {
+                            pos: step.cell,
+                            face,
+                            distance,
+                        })
+                }
+            };
+            if hit.is_some() {
+                return hit;
+            }
+        }
+
+        None
+    }
+}
+
+impl World {
+    /// Find the block of any level that a ray from _origin_ along _direction_ hits first within
+    /// _max_distance_, according to _target_. Returns the index of the level in [`Self::levels`]
+    /// and the hit.
+    ///
+    /// _origin_ and _direction_ are in world coordinates; _direction_ must be a unit vector. When
+    /// blocks of several levels are hit at the same distance, the level that comes first wins.
+    pub fn raycast(
+        &self,
+        origin: Vec3,
+        direction: Vec3,
+        max_distance: Float,
+        target: Target,
+    ) -> Option<(usize, Hit)> {
+        let mut result: Option<(usize, Hit)> = None;
+        for (index, level) in self.levels.iter().enumerate() {
+            let Some(hit) = level.raycast(origin, direction, max_distance, target) else {
+                continue;
+            };
+            if result.is_none_or(|(_, best)| hit.distance < best.distance) {
+                result = Some((index, hit));
+            }
+        }
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        content::block::state,
+        world::{Quat, UVec3, array3::Array3, math},
+    };
+
+    fn empty_level() -> Level {
+        Level::from_blocks(
+            Array3::default(UVec3::new(8, 8, 8)),
+            Vec3::ZERO,
+            Quat::IDENTITY,
+        )
+    }
+
+    #[test]
+    fn cell_and_shape_targets() {
+        let mut level = empty_level();
+        level.set_block(IVec3::new(5, 5, 0), state::parse("plate").unwrap());
+        level.set_block(IVec3::new(5, 5, 3), state::parse("lever").unwrap());
+        let world = World::from_levels(vec![level]);
+        let origin = Vec3::new(5.0, 5.0, 5.0);
+
+        // The lever has no collision shape, so only the plate is hit
+        let (_, hit) = world
+            .raycast(origin, -Vec3::Z, 10.0, Target::Shape)
+            .unwrap();
+        assert_eq!(hit.pos, IVec3::new(5, 5, 0));
+        assert_eq!(hit.face, Some(Direction::Up));
+        assert!((hit.distance - (5.5 - 1.0 / 16.0)).abs() < 1e-5);
+
+        let (_, hit) = world.raycast(origin, -Vec3::Z, 10.0, Target::Cell).unwrap();
+        assert_eq!(hit.pos, IVec3::new(5, 5, 3));
+        assert_eq!(hit.face, Some(Direction::Up));
+        assert_eq!(hit.distance, 1.5);
+
+        // Passes above the plate but through its cell
+        let origin = Vec3::new(0.0, 5.0, -0.2);
+        assert!(
+            world
+                .raycast(origin, Vec3::X, 20.0, Target::Shape)
+                .is_none()
+        );
+        let (_, hit) = world.raycast(origin, Vec3::X, 20.0, Target::Cell).unwrap();
+        assert_eq!(hit.pos, IVec3::new(5, 5, 0));
+    }
+
+    #[test]
+    fn levels_are_transformed() {
+        let mut level = empty_level();
+        level.position = Vec3::new(100.0, 0.0, 0.0);
+        level.rotation = math::rotation_z(-std::f32::consts::FRAC_PI_2);
+        level.set_block(IVec3::new(3, 0, 0), state::parse("stone").unwrap());
+        let world = World::from_levels(vec![empty_level(), level]);
+        let level = &world.levels[1];
+        let center = level.to_world(Vec3::new(3.0, 0.0, 0.0));
+
+        let origin = center + Vec3::new(0.0, 0.0, 5.0);
+        let (index, hit) = world
+            .raycast(origin, -Vec3::Z, 10.0, Target::Shape)
+            .unwrap();
+        assert_eq!(index, 1);
+        assert_eq!(hit.pos, IVec3::new(3, 0, 0));
+        assert_eq!(hit.face, Some(Direction::Up));
+        assert!((hit.distance - 4.5).abs() < 1e-4);
+
+        let direction = level.vector_to_world(Vec3::X);
+        let origin = level.to_world(Vec3::ZERO);
+        let (_, hit) = world
+            .raycast(origin, direction, 10.0, Target::Shape)
+            .unwrap();
+        assert_eq!(hit.face, Some(Direction::West));
+        assert!((hit.distance - 2.5).abs() < 1e-4);
+
+        let (_, hit) = world
+            .raycast(center, direction, 10.0, Target::Shape)
+            .unwrap();
+        assert_eq!((hit.face, hit.distance), (None, 0.0));
+    }
+}
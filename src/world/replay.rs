@@ -0,0 +1,272 @@
+//! Recording and playback of runs.
+//!
+//! Since the [world](World) only changes in reaction to [events](Event) and reactions are
+//! deterministic, a run is fully described by the level it starts in and the sequence of events
+//! that were processed. A [`Replay`] stores exactly that; a [`Player`] feeds the events back into a
+//! fresh world to reproduce the run.
+//!
+//! To detect desyncs, replays also contain periodic [checksums](Checksum) of the world state that
+//! are verified during playback.
+//!
+//! See [`format::replay`](crate::world::format::replay) for the file format.
+
+use crate::{
+    logic::Logic,
+    world::{Event, Level, TARGET_TPS, World, format::LoadError, hash::StateHash},
+};
+
+/// Number of logic ticks between [checksums](Checksum) recorded by the game.
+pub const CHECKSUM_INTERVAL: u32 = TARGET_TPS;
+
+/// Level reference of the hardcoded test level created by [`Level::test`].
+pub const TEST_LEVEL: &str = "builtin:test";
+
+/// Load the level identified by _reference_.
+///
+/// A reference is either [`TEST_LEVEL`] or a path to a level file. Files with the `.txt` extension
+/// are read in [text format](crate::world::format::text), other files in [binary
+/// format](crate::world::format::binary).
+pub fn load_level(reference: &str) -> Result<Level, LoadError> {
+    if reference == TEST_LEVEL {
+        return Ok(Level::test());
+    }
+
+    if reference.ends_with(".txt") {
+        Level::from_text(&std::fs::read_to_string(reference)?)
+    } else {
+        let file = std::fs::File::open(reference)?;
+        Level::load(&mut std::io::BufReader::new(file))
+    }
+}
+
+/// The [state hash](StateHash) of the world at some point of a recorded run.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Checksum {
+    /// The number of events processed before the hash was computed.
+    pub position: usize,
+
+    /// The state hash of the world.
+    pub hash: u64,
+}
+
+/// A recorded run: the level it starts in and every event processed by the world.
+#[derive(Debug, Clone, Default)]
+pub struct Replay {
+    /// Reference to the level the run starts in, as accepted by [`load_level`].
+    pub level: String,
+
+    /// All events passed to [`World::process`], in order.
+    pub events: Vec<Event>,
+
+    /// Checksums of the world state during the run, ordered by position.
+    pub checksums: Vec<Checksum>,
+}
+
+impl Replay {
+    /// Create an empty replay of a run that starts in the level identified by _level_.
+    pub fn new(level: impl Into<String>) -> Self {
+        Self {
+            level: level.into(),
+            events: Vec::new(),
+            checksums: Vec::new(),
+        }
+    }
+
+    /// Append _event_ to the recording. Should be called for every event passed to
+    /// [`World::process`].
+    pub fn record(&mut self, event: &Event) {
+        self.events.push(event.clone());
+    }
+
+    /// Record the current state hash of _world_, which must have processed exactly the events
+    /// recorded so far.
+    pub fn record_checksum(&mut self, world: &World) {
+        self.checksums.push(Checksum {
+            position: self.events.len(),
+            hash: world.checksum(),
+        });
+    }
+}
+
+/// An error reported when the world state during playback does not match a recorded
+/// [`Checksum`].
+#[derive(Debug)]
+pub struct Desync {
+    /// The checksum that did not match.
+    pub expected: Checksum,
+
+    /// The state hash of the world during playback.
+    pub actual: u64,
+}
+
+impl std::fmt::Display for Desync {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "desync after {} events: expected state hash {:016x}, got {:016x}",
+            self.expected.position, self.expected.hash, self.actual
+        )
+    }
+}
+
+impl std::error::Error for Desync {}
+
+/// Reproduces a [`Replay`] by feeding its events into a fresh [`World`] and verifying its
+/// checksums.
+pub struct Player<'a> {
+    replay: &'a Replay,
+    world: World,
+
+    /// Index of the next event to process.
+    next: usize,
+
+    /// Index of the next checksum to verify.
+    next_checksum: usize,
+}
+
+impl<'a> Player<'a> {
+    /// Load the initial level of _replay_ and prepare to play it back.
+    pub fn new(replay: &'a Replay) -> Result<Self, LoadError> {
+        Ok(Self::with_level(replay, load_level(&replay.level)?))
+    }
+
+    /// Prepare to play back _replay_ starting in _level_ instead of the level it references.
+    pub fn with_level(replay: &'a Replay, level: Level) -> Self {
+        Self {
+            replay,
+            world: World::from_levels(vec![level]),
+            next: 0,
+            next_checksum: 0,
+        }
+    }
+
+    /// Get the current state of the world.
+    pub fn world(&self) -> &World {
+        &self.world
+    }
+
+    /// Get the number of checksums verified so far.
+    pub fn checksums_verified(&self) -> usize {
+        self.next_checksum
+    }
+
+    /// Get the number of events processed so far.
+    pub fn position(&self) -> usize {
+        self.next
+    }
+
+    /// Check whether all events have been processed.
+    pub fn is_finished(&self) -> bool {
+        self.next >= self.replay.events.len()
+    }
+
+    /// Verify all checksums recorded at the current position.
+    fn verify(&mut self) -> Result<(), Desync> {
+        while let Some(expected) = self.replay.checksums.get(self.next_checksum) {
+            if expected.position > self.next {
+                break;
+            }
+
+            self.next_checksum += 1;
+            if expected.position < self.next {
+                // Checksums out of order; only possible in hand-crafted replays
+                continue;
+            }
+
+            let actual = self.world.checksum();
+            if actual != expected.hash {
+                return Err(Desync {
+                    expected: *expected,
+                    actual,
+                });
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Process the next event, if any. Returns the processed event.
+    ///
+    /// Checksums recorded before and after the event are verified.
+    pub fn step(&mut self, logic: &Logic) -> Result<Option<&'a Event>, Desync> {
+        self.verify()?;
+
+        let Some(event) = self.replay.events.get(self.next) else {
+            return Ok(None);
+        };
+        self.world.process(event.clone(), logic);
+        self.next += 1;
+
+        self.verify()?;
+        Ok(Some(event))
+    }
+
+    /// Process all remaining events and return the resulting world.
+    pub fn finish(mut self, logic: &Logic) -> Result<World, Desync> {
+        while self.step(logic)?.is_some() {}
+        Ok(self.world)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+    use crate::world::Vec2;
+
+    /// Record a short run in the test level with a checksum after every event.
+    fn record(logic: &Logic) -> (Replay, World) {
+        let mut replay = Replay::new(TEST_LEVEL);
+        let mut world = World::from_levels(vec![Level::test()]);
+        replay.record_checksum(&world);
+
+        let mut events = vec![Event::MovePlayerCharacter { direction: Vec2::X }];
+        for _ in 0..20 {
+            events.push(Event::PresentationTick {
+                duration: Duration::from_millis(16),
+            });
+            events.push(Event::LogicTick);
+        }
+
+        for event in events {
+            replay.record(&event);
+            world.process(event, logic);
+            replay.record_checksum(&world);
+        }
+        (replay, world)
+    }
+
+    #[test]
+    fn playback_reproduces_the_run() {
+        let logic = Logic::new();
+        let (replay, world) = record(&logic);
+
+        let mut player = Player::new(&replay).unwrap();
+        while player.step(&logic).unwrap().is_some() {}
+        assert_eq!(player.checksums_verified(), replay.checksums.len());
+        assert_eq!(player.world().checksum(), world.checksum());
+        assert_ne!(
+            world.checksum(),
+            World::from_levels(vec![Level::test()]).checksum()
+        );
+    }
+
+    #[test]
+    fn checksum_mismatch_is_reported() {
+        let logic = Logic::new();
+        let (mut replay, _) = record(&logic);
+        replay.checksums[5].hash ^= 1;
+
+        let Err(desync) = Player::new(&replay).unwrap().finish(&logic) else {
+            panic!("playback did not detect the desync");
+        };
+        assert_eq!(desync.expected, replay.checksums[5]);
+        assert_eq!(desync.expected.position, 5);
+    }
+
+    #[test]
+    fn unknown_level_is_an_error() {
+        assert!(load_level("no/such/level.txt").is_err());
+    }
+}
@@ -0,0 +1,177 @@
+//! Failure and recovery of the player character: checkpoints, hazards and respawning.
+//!
+//! The player character dies when it touches a [hazard block](crate::content::block::Hazard) or
+//! falls below the [kill plane](Level::kill_height) of every level. A dead character stops moving
+//! until it respawns through [`Event::RespawnPlayerCharacter`], which the client issues
+//! automatically after death and on player request. Because respawning is an event, it is recorded
+//! in replays like any other input.
+//!
+//! Respawning moves the character to the last [`Checkpoint`] it reached: the last
+//! [checkpoint block](crate::content::block::Checkpoint) it touched, or its spawn point if there is
+//! none. Respawns during a run are counted in [`Run::respawns`](crate::world::run::Run::respawns).
+
+use crate::{
+    content::block::Block,
+    world::{
+        Float, Level, UVec3, Vec3, World, YawPitch, character::Character, collision, run::RunState,
+        vec_iter::VecIterators,
+    },
+};
+
+/// Distance between the lowest point of a level and its [kill plane](Level::kill_height).
+pub const KILL_DEPTH: Float = 16.0;
+
+/// The place the player character returns to when it respawns.
+#[derive(Debug, Clone, Copy)]
+pub struct Checkpoint {
+    /// Position of the character (center of feet) in the world coordinate frame.
+    pub position: Vec3,
+
+    /// The direction the character looks in, in the world coordinate frame.
+    pub rotation: YawPitch,
+}
+
+impl Level {
+    /// Get the height in world coordinates of the kill plane of this level, [`KILL_DEPTH`] below
+    /// its lowest point.
+    pub fn kill_height(&self) -> Float {
+        let size = self.blocks.shape().as_vec3();
+        let corners = UVec3::ZERO.iter_box(&UVec3::splat(2));
+        corners
+            .map(|corner| self.to_world(corner.as_vec3() * size - 0.5).z)
+            .fold(Float::INFINITY, Float::min)
+            - KILL_DEPTH
+    }
+}
+
+impl World {
+    /// Kill the player character if it touched a hazard while moving from _previous_ to its
+    /// current position or fell out of all levels, and record checkpoints it touched before that.
+    pub(crate) fn check_hazards(&mut self, previous: Vec3) {
+        if !self.player.alive {
+            return;
+        }
+
+        let body = &Character::BODY;
+        let current = self.player.position;
+        let hazard = collision::first_touch(&self.levels, body, previous, current, |block| {
+            matches!(block, Block::Hazard(_))
+        });
+        let checkpoint = collision::first_touch(&self.levels, body, previous, current, |block| {
+            matches!(block, Block::Checkpoint(_))
+        });
+
+        if let Some(checkpoint) = checkpoint
+            && hazard.is_none_or(|hazard| checkpoint.fraction <= hazard.fraction)
+        {
+            let level = &self.levels[checkpoint.level];
+            self.checkpoint = Checkpoint {
+                position: level.top_of(checkpoint.pos.as_vec3()),
+                rotation: self.player.rotation,
+            };
+        }
+
+        let fell = !self.levels.is_empty()
+            && self
+                .levels
+                .iter()
+                .all(|level| current.z < level.kill_height());
+        if hazard.is_some() || fell {
+            self.player.alive = false;
+            self.player.velocity = Vec3::ZERO;
+        }
+    }
+
+    /// Move the player character to the last checkpoint, reset its velocity and rotation and
+    /// revive it.
+    pub(crate) fn respawn(&mut self) {
+        let player = &mut self.player;
+        player.position = self.checkpoint.position;
+        player.velocity = Vec3::ZERO;
+        player.rotation = self.checkpoint.rotation;
+        player.grounded = false;
+        player.alive = true;
+
+        if self.run.state == RunState::Running {
+            self.run.respawns += 1;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+    use crate::{
+        content::block::{Checkpoint, Hazard, Start, Stone},
+        logic::Logic,
+        world::{Event, Quat, Vec2, array3::Array3},
+    };
+
+    /// A stone floor running east from a start block, over a checkpoint block to a hazard block.
+    fn course() -> World {
+        let mut blocks = Array3::default(UVec3::new(10, 10, 3));
+        for x in 0..9 {
+            for y in 0..10 {
+                blocks[UVec3::new(x, y, 0)] = Block::Stone(Stone);
+            }
+        }
+        blocks[UVec3::new(1, 5, 0)] = Block::Start(Start);
+        blocks[UVec3::new(3, 5, 0)] = Block::Checkpoint(Checkpoint);
+        blocks[UVec3::new(6, 5, 0)] = Block::Hazard(Hazard);
+        World::from_levels(vec![Level::from_blocks(blocks, Vec3::ZERO, Quat::IDENTITY)])
+    }
+
+    /// Move the player character along _direction_ until it dies.
+    fn run_until_death(world: &mut World, logic: &Logic, direction: Vec2) {
+        world.process(Event::MovePlayerCharacter { direction }, logic);
+        for _ in 0..1000 {
+            if !world.player.alive {
+                return;
+            }
+            let duration = Duration::from_millis(10);
+            world.process(Event::PresentationTick { duration }, logic);
+        }
+        panic!("player character never died");
+    }
+
+    #[test]
+    fn hazard_kills_and_respawn_returns_to_checkpoint() {
+        let logic = Logic::new();
+        let mut world = course();
+        assert_eq!(world.checkpoint.position, Vec3::new(1.0, 5.0, 0.5));
+
+        run_until_death(&mut world, &logic, Vec2::X);
+        let checkpoint = Vec3::new(3.0, 5.0, 0.5);
+        assert_eq!(world.checkpoint.position, checkpoint);
+        assert_eq!(world.player.velocity, Vec3::ZERO);
+
+        // Dead characters do not move
+        let position = world.player.position;
+        let duration = Duration::from_millis(10);
+        world.process(Event::PresentationTick { duration }, &logic);
+        assert_eq!(world.player.position, position);
+
+        world.process(Event::RespawnPlayerCharacter, &logic);
+        assert!(world.player.alive);
+        assert_eq!(world.player.position, checkpoint);
+        assert_eq!(world.player.velocity, Vec3::ZERO);
+        assert_eq!(world.run.respawns, 1);
+        assert_eq!(world.run.state, RunState::Running);
+    }
+
+    #[test]
+    fn falling_below_kill_plane_kills() {
+        let logic = Logic::new();
+        let mut world = course();
+        assert_eq!(world.levels[0].kill_height(), -0.5 - KILL_DEPTH);
+
+        run_until_death(&mut world, &logic, -Vec2::Y);
+        assert!(world.player.position.z < world.levels[0].kill_height());
+
+        world.process(Event::RespawnPlayerCharacter, &logic);
+        assert_eq!(world.player.position, Vec3::new(1.0, 5.0, 0.5));
+        assert_eq!(world.run.respawns, 1);
+    }
+}
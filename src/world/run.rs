@@ -0,0 +1,225 @@
+//! Runs: timed attempts to get the player character from the start to a finish block.
+//!
+//! A [`Run`] starts with the first movement input of the player, i.e. the first
+//! [`Event::MovePlayerCharacter`] with a non-zero direction or the first
+//! [`Event::JumpPlayerCharacter`], and finishes as soon as the body of the character touches the
+//! cell of a [finish block](crate::content::block::Finish).
+//!
+//! Run time is measured in simulation time, so it does not depend on the frame rate or the speed
+//! of the machine, and replays reproduce it exactly. It is the sum of the durations of all
+//! presentation ticks during the run, where the tick in which the character reaches the finish
+//! only counts up to the moment of contact. The moment is interpolated assuming that the character
+//! moved in a straight line during the tick. Logic ticks during the run are counted separately.
+//!
+//! Loading a [savestate](crate::world::savestate) turns the run into a [practice
+//! run](Run::practice), which cannot set records. The mark persists through restarts, since the
+//! loaded state may differ from the original level in ways a restart does not undo.
+
+use std::time::Duration;
+
+use crate::{
+    content::block::Block,
+    world::{Event, Level, Vec2, Vec3, character::Character, collision},
+};
+
+/// The progress of a [`Run`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum RunState {
+    /// The player has not moved yet.
+    #[default]
+    NotStarted,
+
+    /// The player has moved and not reached the finish yet.
+    Running,
+
+    /// The player character has touched a finish block. Run time no longer changes.
+    Finished,
+}
+
+/// The state of the timed attempt of the player at the world.
+#[derive(Debug, Clone, Default)]
+pub struct Run {
+    /// Whether the run has started or finished.
+    pub state: RunState,
+
+    /// Simulation time since the run started, up to the moment the character reached the finish
+    /// for finished runs. Saturates at [`Duration::MAX`].
+    pub time: Duration,
+
+    /// The number of logic ticks since the run started, up to the finish for finished runs.
+    pub logic_ticks: u64,
+
+    /// The number of times the player character [respawned](crate::world::respawn) during the
+    /// run.
+    pub respawns: u32,
+
+    /// Whether a [savestate](crate::world::savestate) was loaded during or before the run.
+    pub practice: bool,
+}
+
+impl Run {
+    /// Create a run that has not started yet.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Get the final run time, or `None` if the run has not finished.
+    pub fn final_time(&self) -> Option<Duration> {
+        match self.state {
+            RunState::Finished => Some(self.time),
+            _ => None,
+        }
+    }
+
+    /// Get the final run time if the run counts as a record attempt, or `None` if the run has not
+    /// finished or is a [practice](Self::practice) run.
+    pub fn record_time(&self) -> Option<Duration> {
+        self.final_time().filter(|_| !self.practice)
+    }
+
+    /// Update the run according to _event_.
+    ///
+    /// _previous_ is the position of the player character before it processed _event_, and
+    /// _levels_ and _character_ are the state after it.
+    pub(crate) fn process(
+        &mut self,
+        event: &Event,
+        previous: Vec3,
+        levels: &[Level],
+        character: &Character,
+    ) {
+        match event {
+            Event::MovePlayerCharacter { direction } if *direction != Vec2::ZERO => {
+                self.start();
+            }
+            Event::JumpPlayerCharacter => self.start(),
+            Event::LogicTick if self.state == RunState::Running => self.logic_ticks += 1,
+            Event::PresentationTick { duration } if self.state == RunState::Running => {
+                let touch = collision::first_touch(
+                    levels,
+                    &Character::BODY,
+                    previous,
+                    character.position,
+                    |block| matches!(block, Block::Finish(_)),
+                );
+
+                let counted = match touch {
+                    Some(touch) => {
+                        self.state = RunState::Finished;
+                        // Same as Duration::mul_f64, which panics on durations close to the limit
+                        Duration::try_from_secs_f64(touch.fraction as f64 * duration.as_secs_f64())
+                            .unwrap_or(*duration)
+                    }
+                    None => *duration,
+                };
+                self.time = self.time.saturating_add(counted);
+            }
+            _ => {}
+        }
+    }
+
+    /// Start the run if it has not started yet.
+    pub(crate) fn start(&mut self) {
+        if self.state == RunState::NotStarted {
+            self.state = RunState::Running;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use super::*;
+    use crate::{
+        content::block::{Finish, Start, Stone},
+        logic::Logic,
+        world::{Quat, UVec3, World, array3::Array3, savestate::Savestate},
+    };
+
+    const TICK: Duration = Duration::from_millis(10);
+
+    /// A stone floor with a start block at its west end and a finish block above its east end.
+    fn track() -> World {
+        let mut blocks = Array3::default(UVec3::new(10, 10, 3));
+        for x in 0..10 {
+            for y in 0..10 {
+                blocks[UVec3::new(x, y, 0)] = Block::Stone(Stone);
+            }
+        }
+        blocks[UVec3::new(1, 5, 0)] = Block::Start(Start);
+        blocks[UVec3::new(8, 5, 1)] = Block::Finish(Finish);
+        World::from_levels(vec![Level::from_blocks(blocks, Vec3::ZERO, Quat::IDENTITY)])
+    }
+
+    fn tick(world: &mut World, logic: &Logic) {
+        world.process(Event::PresentationTick { duration: TICK }, logic);
+    }
+
+    #[test]
+    fn run_starts_with_first_movement() {
+        let logic = Logic::new();
+        let mut world = track();
+        assert_eq!(world.player.position, Vec3::new(1.0, 5.0, 0.5));
+
+        for _ in 0..10 {
+            tick(&mut world, &logic);
+        }
+        world.process(Event::LogicTick, &logic);
+        let direction = Vec2::ZERO;
+        world.process(Event::MovePlayerCharacter { direction }, &logic);
+        assert_eq!(world.run.state, RunState::NotStarted);
+        assert_eq!(world.run.time, Duration::ZERO);
+        assert_eq!(world.run.logic_ticks, 0);
+
+        world.process(Event::JumpPlayerCharacter, &logic);
+        assert_eq!(world.run.state, RunState::Running);
+        assert_eq!(world.run.final_time(), None);
+    }
+
+    #[test]
+    fn run_finishes_at_moment_of_contact() {
+        let logic = Logic::new();
+        let mut world = track();
+        for _ in 0..10 {
+            tick(&mut world, &logic);
+        }
+        let direction = Vec2::X;
+        world.process(Event::MovePlayerCharacter { direction }, &logic);
+
+        let mut ticks = 0;
+        while world.run.state == RunState::Running {
+            tick(&mut world, &logic);
+            ticks += 1;
+            if ticks % 5 == 0 {
+                world.process(Event::LogicTick, &logic);
+            }
+            assert!(ticks < 1000, "character never reached the finish");
+        }
+
+        let time = world.run.final_time().unwrap();
+        assert!(time > TICK * (ticks - 1) && time <= TICK * ticks);
+        assert_eq!(world.run.logic_ticks, (ticks as u64 - 1) / 5);
+        assert_eq!(world.run.record_time(), Some(time));
+
+        for _ in 0..10 {
+            tick(&mut world, &logic);
+            world.process(Event::LogicTick, &logic);
+        }
+        assert_eq!(world.run.final_time(), Some(time));
+    }
+
+    #[test]
+    fn loading_savestate_makes_practice_run() {
+        let logic = Logic::new();
+        let mut world = track();
+        let savestate = Arc::new(Savestate::capture(&world));
+        world.process(Event::LoadSavestate { savestate }, &logic);
+        world.process(Event::Restart, &logic);
+        assert!(world.run.practice);
+
+        world.run.state = RunState::Finished;
+        assert!(world.run.final_time().is_some());
+        assert_eq!(world.run.record_time(), None);
+    }
+}
@@ -0,0 +1,58 @@
+//! Savestates: snapshots of the complete state of a world, used to practice sections of a level.
+//!
+//! A [`Savestate`] captures everything about a [`World`]: the blocks, initial blocks and pending
+//! [block updates](crate::world::update) of every level, the player character including its
+//! movement controls, the [run](crate::world::run) and the [checkpoints](crate::world::respawn).
+//! Loading it restores that state exactly, so the simulation continues as if it had never left the
+//! captured moment.
+//!
+//! Savestates are loaded through [`Event::LoadSavestate`](crate::world::Event::LoadSavestate),
+//! which carries the savestate itself. Replays therefore still reproduce sessions that loaded
+//! savestates. Loading marks the run as a [practice run](crate::world::run::Run::practice).
+//!
+//! Savestates live in memory and can be written to files in [savestate
+//! format](crate::world::format::savestate).
+
+use crate::world::World;
+
+/// A snapshot of the complete state of a [`World`].
+#[derive(Clone)]
+pub struct Savestate {
+    world: World,
+}
+
+impl Savestate {
+    /// Create a savestate that restores _world_.
+    pub fn new(world: World) -> Self {
+        Self { world }
+    }
+
+    /// Capture the current state of _world_.
+    pub fn capture(world: &World) -> Self {
+        Self::new(world.clone())
+    }
+
+    /// Get the captured state.
+    pub fn world(&self) -> &World {
+        &self.world
+    }
+}
+
+impl std::fmt::Debug for Savestate {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Savestate")
+            .field("levels", &self.world.levels.len())
+            .field("position", &self.world.player.position)
+            .field("run", &self.world.run)
+            .finish_non_exhaustive()
+    }
+}
+
+impl World {
+    /// Replace the entire state of this world with the state captured in _savestate_ and mark the
+    /// run as a [practice run](crate::world::run::Run::practice).
+    pub(crate) fn load_savestate(&mut self, savestate: &Savestate) {
+        self.clone_from(&savestate.world);
+        self.run.practice = true;
+    }
+}
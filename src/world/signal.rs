@@ -0,0 +1,43 @@
+//! Signal power: the wiring that connects switches to the mechanisms they control.
+//!
+//! Blocks play one of three roles in signal networks:
+//! - _sources_ such as levers, buttons and pressure plates emit [`MAX_POWER`] while they are
+//!   active,
+//! - _conductors_ pass power on: wires take on the strongest power of their neighbours minus one,
+//!   and relays pass full power on in one direction after a configurable delay,
+//! - _consumers_ such as pushers and doors react when they become [powered](is_powered).
+//!
+//! Every block declares the power it emits towards each of its neighbours with
+//! [`Instance::signal_output`](crate::content::block::Instance::signal_output). Signals only
+//! travel between blocks that share a face.
+//!
+//! Signals propagate through [block updates](crate::world::update): when a block changes, its
+//! neighbours are notified in the next logic tick and recompute their power. Each conductor thus
+//! adds one logic tick of delay, and relays add their own configurable delay on top. Since power
+//! decreases with every wire, a loop of wires cannot keep itself powered once its sources turn off:
+//! its power counts down until it runs out.
+
+use crate::world::{Direction, IVec3, Level};
+
+/// The strength of a signal, from `0` for no signal to [`MAX_POWER`].
+pub type Power = u8;
+
+/// The power emitted by active sources.
+pub const MAX_POWER: Power = 15;
+
+/// Get the strongest power that the neighbours of the block at _pos_ in _level_ emit towards it.
+pub fn input(level: &Level, pos: IVec3) -> Power {
+    Direction::ALL
+        .into_iter()
+        .filter_map(|direction| {
+            let neighbour = level.block(pos + direction.offset())?;
+            Some(neighbour.signal_output(direction.opposite()))
+        })
+        .max()
+        .unwrap_or(0)
+}
+
+/// Check whether any neighbour of the block at _pos_ in _level_ emits power towards it.
+pub fn is_powered(level: &Level, pos: IVec3) -> bool {
+    input(level, pos) > 0
+}
@@ -0,0 +1,102 @@
+//! Conversion between world coordinates and the coordinate frames of levels.
+//!
+//! Every level is placed in the world by a rigid transform: a point _p_ in level coordinates is at
+//! `level.position + level.rotation * p` in world coordinates. The [rotation](Level::rotation) is
+//! an arbitrary unit quaternion, so levels can be tilted or upside down.
+//!
+//! All simulation code, such as collisions and raycasts, converts between coordinate frames with
+//! the methods here. They use [`math`] and are therefore bit-deterministic, unlike the matrix
+//! returned by [`Level::transform`], which is meant for rendering.
+//!
+//! Bodies such as the player character are boxes aligned with the world axes, but the cells of a
+//! level are aligned with its grid. Collision code approximates bodies by [boxes aligned with the
+//! grid](Level::body_to_local) and treats the [grid direction closest to world up](Level::up) as
+//! up, so that characters can stand on whatever surface of a rotated level faces upwards.
+
+use crate::world::{Affine3, Direction, Level, Vec3, collision::Aabb, math};
+
+impl Level {
+    /// Convert a point from world coordinates to the coordinate frame of this level.
+    pub fn to_local(&self, point: Vec3) -> Vec3 {
+        self.vector_to_local(point - self.position)
+    }
+
+    /// Convert a point from the coordinate frame of this level to world coordinates.
+    pub fn to_world(&self, point: Vec3) -> Vec3 {
+        self.position + self.vector_to_world(point)
+    }
+
+    /// Convert a direction or displacement from world coordinates to the coordinate frame of this
+    /// level. Unlike [`Self::to_local`], level position is ignored.
+    pub fn vector_to_local(&self, vector: Vec3) -> Vec3 {
+        math::rotate(self.rotation.conjugate(), vector)
+    }
+
+    /// Convert a direction or displacement from the coordinate frame of this level to world
+    /// coordinates. Unlike [`Self::to_world`], level position is ignored.
+    pub fn vector_to_world(&self, vector: Vec3) -> Vec3 {
+        math::rotate(self.rotation, vector)
+    }
+
+    /// Get the transform from the coordinate frame of this level to world coordinates as a matrix.
+    ///
+    /// The result is not bit-deterministic; use it for rendering only.
+    pub fn transform(&self) -> Affine3 {
+        Affine3::from_rotation_translation(self.rotation, self.position)
+    }
+
+    /// Get the direction of the level grid that is closest to world up, i.e. positive world Z.
+    ///
+    /// Collisions with blocks of this level treat their faces in this direction as floors.
+    pub fn up(&self) -> Direction {
+        let up = self.vector_to_local(Vec3::Z);
+
+        // Ties prefer the lower axis
+        let axis = (0..3)
+            .max_by(|a, b| up[*a].abs().total_cmp(&up[*b].abs()).then(b.cmp(a)))
+            .unwrap();
+        Direction::along(axis, up[axis] >= 0.0)
+    }
+
+    /// Approximate _body_, a box aligned with the world axes relative to a point in world
+    /// coordinates, by a box aligned with the grid of this level relative to the same point in
+    /// level coordinates.
+    ///
+    /// The center of the box is converted exactly. Its extents along world Z are assigned to the
+    /// [up axis](Self::up) of the level, and the extents along world X and Y to the remaining axes
+    /// closest to them. For levels that are only rotated around Z by multiples of 90 degrees, the
+    /// result is exact.
+    pub fn body_to_local(&self, body: &Aabb) -> Aabb {
+        let center = self.vector_to_local((body.min + body.max) * 0.5);
+        let extents = (body.max - body.min) * 0.5;
+
+        let up = self.up().axis();
+        let x = self.vector_to_local(Vec3::X);
+        let [first, second] = match up {
+            0 => [1, 2],
+            1 => [0, 2],
+            _ => [0, 1],
+        };
+        let (x_axis, y_axis) = if x[first].abs() >= x[second].abs() {
+            (first, second)
+        } else {
+            (second, first)
+        };
+
+        let mut local_extents = Vec3::ZERO;
+        local_extents[x_axis] = extents.x;
+        local_extents[y_axis] = extents.y;
+        local_extents[up] = extents.z;
+
+        Aabb {
+            min: center - local_extents,
+            max: center + local_extents,
+        }
+    }
+
+    /// Get the point in world coordinates at the center of the face of the cell at _pos_ that faces
+    /// [up](Self::up), i.e. where a body standing on a block at _pos_ would be.
+    pub fn top_of(&self, pos: Vec3) -> Vec3 {
+        self.to_world(pos + self.up().offset().as_vec3() * 0.5)
+    }
+}
@@ -0,0 +1,461 @@
+//! Block updates: the way blocks react to logic ticks and to changes around them.
+//!
+//! A block reacts to the world only when it receives an [`Update`], which happens in five cases:
+//! - the block requested it earlier with [`Level::schedule_update`],
+//! - the block itself was placed with [`Level::set_block`],
+//! - a neighbouring block was changed with [`Level::set_block`],
+//! - the block was activated with [`Level::trigger`], or
+//! - a body such as the player character is in the cell of the block during a logic tick.
+//!
+//! Updates never run immediately. They are queued in the [`Queue`] of the level and run during a
+//! later [logic tick](crate::world::Event::LogicTick), so a block that changes in reaction to an
+//! update cannot cause an endless chain of updates within a single tick. Updates of occupied
+//! cells are not queued; they run at the start of every logic tick.
+//!
+//! Updates that are due in the same tick run in the order they were queued in, which only depends
+//! on the events processed so far; this keeps block updates deterministic. Neighbour, placement and
+//! occupation updates of a cell that was changed earlier in the same tick are dropped: they were
+//! meant for the replaced block, and the new block is updated in the next tick anyway. Activations
+//! and scheduled updates of such a cell are postponed to the next tick instead, so that player
+//! input and timers are never lost.
+//!
+//! What a block does when it is updated is declared by its kind, see
+//! [`Instance::update`](crate::content::block::Instance::update).
+
+use std::collections::{BTreeMap, BTreeSet};
+
+use crate::{
+    content::block::Block,
+    world::{Direction, IVec3, Level},
+};
+
+/// The reason a block is being updated.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Cause {
+    /// The block requested this update with [`Level::schedule_update`].
+    Scheduled,
+
+    /// The block was placed with [`Level::set_block`].
+    Placed,
+
+    /// The neighbouring block in the given direction has changed.
+    Neighbour(Direction),
+
+    /// The block was activated with [`Level::trigger`].
+    Triggered,
+
+    /// A body is in the cell of the block, see [`Level::logic_tick`].
+    Occupied,
+}
+
+impl Cause {
+    /// Get the numeric code of this cause used in hashes and files: `0` for
+    /// [`Self::Scheduled`], `1` plus the [index](Direction::index) of the direction for
+    /// [`Self::Neighbour`], `7` for [`Self::Triggered`], `8` for [`Self::Placed`] and `9` for
+    /// [`Self::Occupied`].
+    pub fn code(self) -> u8 {
+        match self {
+            Self::Scheduled => 0,
+            Self::Neighbour(direction) => 1 + direction.index() as u8,
+            Self::Triggered => 7,
+            Self::Placed => 8,
+            Self::Occupied => 9,
+        }
+    }
+
+    /// Find the cause with the given [code](Self::code).
+    pub fn from_code(code: u8) -> Option<Self> {
+        match code {
+            0 => Some(Self::Scheduled),
+            1..=6 => Some(Self::Neighbour(Direction::ALL[code as usize - 1])),
+            7 => Some(Self::Triggered),
+            8 => Some(Self::Placed),
+            9 => Some(Self::Occupied),
+            _ => None,
+        }
+    }
+}
+
+/// A pending update of a single block.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Update {
+    /// Position of the block in level coordinates.
+    pub pos: IVec3,
+
+    /// The reason for the update.
+    pub cause: Cause,
+}
+
+/// Updates of the blocks of a level that are waiting for their logic tick, in execution order.
+#[derive(Debug, Clone, Default)]
+pub struct Queue {
+    /// The number of logic ticks the level has received.
+    tick: u64,
+
+    /// The number of updates ever queued; used to order updates that are due in the same tick.
+    queued: u64,
+
+    /// Pending updates keyed by their due tick and the order they were queued in.
+    pending: BTreeMap<(u64, u64), Update>,
+
+    /// Due ticks, positions and causes of pending updates, used to drop duplicate updates.
+    index: BTreeSet<(u64, [i32; 3], Cause)>,
+}
+
+impl Queue {
+    /// Create an empty queue.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Create a queue of a level that has received _tick_ logic ticks, with _pending_ updates and
+    /// their due ticks in execution order, e.g. to restore a queue from [`Self::iter`].
+    ///
+    /// Updates that duplicate an earlier one are dropped, as with [`Self::push`].
+    pub fn from_pending(tick: u64, pending: impl IntoIterator<Item = (u64, Update)>) -> Self {
+        let mut result = Self {
+            tick,
+            ..Self::default()
+        };
+        for (due, update) in pending {
+            result.insert(due, update);
+        }
+        result
+    }
+
+    /// Get the number of logic ticks the level has received.
+    pub fn tick(&self) -> u64 {
+        self.tick
+    }
+
+    /// Iterate over pending updates with their due ticks in execution order.
+    pub fn iter(&self) -> impl Iterator<Item = (u64, &Update)> {
+        self.pending.iter().map(|((due, _), update)| (*due, update))
+    }
+
+    /// Check whether no updates are pending.
+    pub fn is_empty(&self) -> bool {
+        self.pending.is_empty()
+    }
+
+    /// Queue _update_ to run _delay_ logic ticks from now.
+    ///
+    /// A _delay_ of zero is treated as one: the update runs in the next logic tick. An update with
+    /// the same position, cause and due tick as a pending one is dropped.
+    pub fn push(&mut self, update: Update, delay: u64) {
+        let due = self.tick.saturating_add(delay.max(1));
+        self.insert(due, update);
+    }
+
+    /// Queue _update_ to run in logic tick _due_ unless it duplicates a pending update.
+    fn insert(&mut self, due: u64, update: Update) {
+        let key = (due, update.pos.to_array(), update.cause);
+        if self.index.insert(key) {
+            self.pending.insert((due, self.queued), update);
+            self.queued += 1;
+        }
+    }
+
+    /// Begin a new logic tick.
+    fn advance(&mut self) {
+        self.tick += 1;
+    }
+
+    /// Check whether the block at _pos_ was placed during the current tick, i.e. its
+    /// [`Cause::Placed`] update is due in the next tick.
+    fn placed_this_tick(&self, pos: IVec3) -> bool {
+        let key = (self.tick + 1, pos.to_array(), Cause::Placed);
+        self.index.contains(&key)
+    }
+
+    /// Remove and return the first update that is due in the current tick, if any.
+    fn pop_due(&mut self) -> Option<Update> {
+        let entry = self.pending.first_entry()?;
+        let due = entry.key().0;
+        if due > self.tick {
+            return None;
+        }
+
+        let update = entry.remove();
+        let key = (due, update.pos.to_array(), update.cause);
+        self.index.remove(&key);
+        Some(update)
+    }
+}
+
+/// Access to the level for a block that is being updated.
+pub struct Context<'a> {
+    /// The level of the block.
+    pub level: &'a mut Level,
+
+    /// The update being processed.
+    pub update: Update,
+
+    /// Cells of the level that bodies are in during this logic tick.
+    pub occupied: &'a [IVec3],
+}
+
+impl Context<'_> {
+    /// Get the position of the updated block in level coordinates.
+    pub fn pos(&self) -> IVec3 {
+        self.update.pos
+    }
+
+    /// Get the reason for the update.
+    pub fn cause(&self) -> Cause {
+        self.update.cause
+    }
+
+    /// Get the block next to the updated block in _direction_, or `None` if it is outside the
+    /// level.
+    pub fn neighbour(&self, direction: Direction) -> Option<&Block> {
+        self.level.block(self.pos() + direction.offset())
+    }
+
+    /// Check whether a body is in the cell of the updated block during this logic tick.
+    pub fn is_occupied(&self) -> bool {
+        self.occupied.contains(&self.pos())
+    }
+
+    /// Replace the updated block with _block_. See [`Level::set_block`].
+    pub fn set_self(&mut self, block: Block) {
+        self.level.set_block(self.pos(), block);
+    }
+
+    /// Request another update of the updated block _delay_ logic ticks from now. See
+    /// [`Level::schedule_update`].
+    pub fn schedule_self(&mut self, delay: u64) {
+        self.level.schedule_update(self.pos(), delay);
+    }
+}
+
+impl Level {
+    /// Replace the block at _pos_ in level coordinates with _block_, then update it and notify
+    /// its neighbours in the next logic tick.
+    ///
+    /// Positions outside the level are ignored.
+    pub fn set_block(&mut self, pos: IVec3, block: Block) {
+        if self.block(pos).is_none() {
+            return;
+        }
+        self.blocks[pos.as_uvec3()] = block;
+
+        let update = Update {
+            pos,
+            cause: Cause::Placed,
+        };
+        self.updates.push(update, 1);
+
+        for direction in Direction::ALL {
+            let update = Update {
+                pos: pos + direction.offset(),
+                cause: Cause::Neighbour(direction.opposite()),
+            };
+            if self.block(update.pos).is_some() {
+                self.updates.push(update, 1);
+            }
+        }
+    }
+
+    /// Request an update of the block at _pos_ in level coordinates _delay_ logic ticks from now.
+    ///
+    /// A _delay_ of zero is treated as one. Positions outside the level are ignored.
+    pub fn schedule_update(&mut self, pos: IVec3, delay: u64) {
+        if self.block(pos).is_some() {
+            let update = Update {
+                pos,
+                cause: Cause::Scheduled,
+            };
+            self.updates.push(update, delay);
+        }
+    }
+
+    /// Activate the block at _pos_ in level coordinates in the next logic tick. What activation
+    /// does depends on the block; most blocks ignore it.
+    ///
+    /// Positions outside the level are ignored.
+    pub fn trigger(&mut self, pos: IVec3) {
+        if self.block(pos).is_some() {
+            let update = Update {
+                pos,
+                cause: Cause::Triggered,
+            };
+            self.updates.push(update, 1);
+        }
+    }
+
+    /// Process a logic tick: update the blocks in cells that bodies are in, listed in _occupied_
+    /// in level coordinates, then run all block updates that are due.
+    pub fn logic_tick(&mut self, occupied: &[IVec3]) {
+        self.updates.advance();
+
+        for &pos in occupied {
+            let update = Update {
+                pos,
+                cause: Cause::Occupied,
+            };
+            self.run_update(update, occupied);
+        }
+        while let Some(update) = self.updates.pop_due() {
+            self.run_update(update, occupied);
+        }
+    }
+
+    /// Run _update_ during a logic tick in which bodies are in _occupied_ cells.
+    fn run_update(&mut self, update: Update, occupied: &[IVec3]) {
+        if self.updates.placed_this_tick(update.pos) {
+            if matches!(update.cause, Cause::Triggered | Cause::Scheduled) {
+                self.updates.push(update, 1);
+            }
+            return;
+        }
+        let Some(block) = self.block(update.pos).cloned() else {
+            return;
+        };
+        block.update(&mut Context {
+            level: self,
+            update,
+            occupied,
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        content::block::{Button, Stone},
+        world::{Quat, UVec3, Vec3, array3::Array3},
+    };
+
+    fn update(x: i32, cause: Cause) -> Update {
+        Update {
+            pos: IVec3::new(x, 0, 0),
+            cause,
+        }
+    }
+
+    fn drain(queue: &mut Queue) -> Vec<Update> {
+        std::iter::from_fn(|| queue.pop_due()).collect()
+    }
+
+    fn empty_level() -> Level {
+        Level::from_blocks(Array3::default(UVec3::splat(8)), Vec3::ZERO, Quat::IDENTITY)
+    }
+
+    #[test]
+    fn updates_run_by_due_tick_then_queue_order() {
+        let mut queue = Queue::new();
+        queue.push(update(0, Cause::Scheduled), 2);
+        queue.push(update(1, Cause::Triggered), 1);
+        queue.push(update(2, Cause::Placed), 0);
+        queue.push(update(3, Cause::Scheduled), 2);
+
+        assert!(drain(&mut queue).is_empty());
+        queue.advance();
+        assert_eq!(
+            drain(&mut queue),
+            [update(1, Cause::Triggered), update(2, Cause::Placed)]
+        );
+        queue.advance();
+        assert_eq!(
+            drain(&mut queue),
+            [update(0, Cause::Scheduled), update(3, Cause::Scheduled)]
+        );
+        assert!(queue.is_empty());
+    }
+
+    #[test]
+    fn duplicate_updates_are_dropped() {
+        let mut queue = Queue::new();
+        queue.push(update(0, Cause::Scheduled), 1);
+        queue.push(update(0, Cause::Scheduled), 1);
+        queue.push(update(0, Cause::Triggered), 1);
+        queue.push(update(0, Cause::Scheduled), 2);
+        assert_eq!(queue.iter().count(), 3);
+
+        queue.advance();
+        assert_eq!(drain(&mut queue).len(), 2);
+
+        // An update that already ran can be queued again
+        queue.push(update(0, Cause::Triggered), 1);
+        assert_eq!(queue.iter().count(), 2);
+
+        let restored = Queue::from_pending(
+            5,
+            [(6, update(0, Cause::Placed)), (6, update(0, Cause::Placed))],
+        );
+        assert_eq!(restored.tick(), 5);
+        assert_eq!(restored.iter().count(), 1);
+    }
+
+    #[test]
+    fn placing_a_block_updates_it_and_its_neighbours() {
+        let mut level = empty_level();
+        let pos = IVec3::new(3, 3, 3);
+        level.set_block(pos, Block::Stone(Stone));
+
+        let pending: Vec<_> = level.updates.iter().map(|(due, u)| (due, *u)).collect();
+        assert_eq!(pending.len(), 7);
+        assert_eq!(
+            pending[0],
+            (
+                1,
+                Update {
+                    pos,
+                    cause: Cause::Placed
+                }
+            )
+        );
+        for direction in Direction::ALL {
+            let neighbour = Update {
+                pos: pos + direction.offset(),
+                cause: Cause::Neighbour(direction.opposite()),
+            };
+            assert!(pending.contains(&(1, neighbour)), "{neighbour:?}");
+        }
+
+        // Neighbours outside the level are not updated
+        level.logic_tick(&[]);
+        level.set_block(IVec3::ZERO, Block::Stone(Stone));
+        assert_eq!(level.updates.iter().count(), 4);
+        level.schedule_update(IVec3::new(-1, 0, 0), 1);
+        assert_eq!(level.updates.iter().count(), 4);
+    }
+
+    #[test]
+    fn activation_of_a_replaced_cell_is_postponed() {
+        let mut level = empty_level();
+        let pos = IVec3::new(3, 3, 3);
+        level.blocks[pos.as_uvec3()] = Block::Button(Button { pressed: true });
+        let scheduled = Update {
+            pos,
+            cause: Cause::Scheduled,
+        };
+        let triggered = Update {
+            pos,
+            cause: Cause::Triggered,
+        };
+        level.updates = Queue::from_pending(0, [(1, scheduled), (1, triggered)]);
+
+        // The scheduled update releases the button, replacing it, so the activation waits for the
+        // next tick
+        level.logic_tick(&[]);
+        assert!(matches!(
+            level.block(pos),
+            Some(Block::Button(Button { pressed: false }))
+        ));
+        assert!(
+            level
+                .updates
+                .iter()
+                .any(|(due, u)| due == 2 && *u == triggered)
+        );
+
+        level.logic_tick(&[]);
+        assert!(matches!(
+            level.block(pos),
+            Some(Block::Button(Button { pressed: true }))
+        ));
+    }
+}
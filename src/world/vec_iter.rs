@@ -1,6 +1,6 @@
 //! Various iterators based on glam integer vectors.
 
-use crate::world::UVec3;
+use crate::world::{Direction, Float, IVec3, UVec3, Vec3};
 
 /// Iterates all positions in the cuboid delimited by two points. See [`VecIterators::iter_box`].
 pub struct Vec3BoxIter {
@@ -96,6 +96,102 @@ impl Iterator for Vec3BoxIter {
 
 // ExactSizeIterator is not implemented because volume may be greater than usize::MAX.
 
+/// A cell of a grid visited by a [`GridRayIter`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct GridRayStep {
+    /// Position of the cell.
+    pub cell: IVec3,
+
+    /// Distance along the ray from its origin to the point where it enters the cell, in multiples
+    /// of the length of the ray direction. Zero for the cell that contains the origin.
+    pub distance: Float,
+
+    /// The face of the cell that the ray enters through, or `None` for the cell that contains the
+    /// origin.
+    pub face: Option<Direction>,
+}
+
+/// Iterates all cells of a grid that a ray passes through, in order. See [`GridRayIter::new`].
+pub struct GridRayIter {
+    /// Origin of the ray.
+    origin: Vec3,
+
+    /// Direction of the ray.
+    direction: Vec3,
+
+    /// Sign of the direction along each axis, or zero for axes the ray does not move along.
+    step: IVec3,
+
+    /// The next step that will be returned.
+    next: GridRayStep,
+}
+
+impl GridRayIter {
+    /// Trace a ray from _origin_ along _direction_ through a grid of unit cells centered on
+    /// integer coordinates, such as the blocks of a level.
+    ///
+    /// The iterator never ends unless _direction_ is zero or not finite; limit it by
+    /// [distance](GridRayStep::distance), e.g. with [`Iterator::take_while`]. Distances are
+    /// computed from the origin for every cell rather than accumulated, so they stay exact far
+    /// from the origin. When the ray passes exactly through an edge or a corner, some cells that
+    /// the ray only touches there may be visited as well.
+    pub fn new(origin: Vec3, direction: Vec3) -> Self {
+        let step = IVec3::from_array(direction.to_array().map(|coord| {
+            if coord > 0.0 {
+                1
+            } else if coord < 0.0 {
+                -1
+            } else {
+                0
+            }
+        }));
+
+        Self {
+            origin,
+            direction,
+            step,
+            next: GridRayStep {
+                cell: (origin + 0.5).floor().as_ivec3(),
+                distance: 0.0,
+                face: None,
+            },
+        }
+    }
+
+    /// Compute the distance along the ray to the boundary where it leaves the current cell along
+    /// _axis_, or infinity if it never does.
+    fn exit_distance(&self, axis: usize) -> Float {
+        if self.step[axis] == 0 {
+            return Float::INFINITY;
+        }
+        let boundary = self.next.cell[axis] as Float + 0.5 * self.step[axis] as Float;
+        (boundary - self.origin[axis]) / self.direction[axis]
+    }
+}
+
+impl Iterator for GridRayIter {
+    type Item = GridRayStep;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let result = self.next;
+        if !result.distance.is_finite() {
+            return None;
+        }
+
+        let exits = [0, 1, 2].map(|axis| self.exit_distance(axis));
+        let axis = Vec3::from_array(exits).min_position();
+        let mut cell = result.cell;
+        cell[axis] += self.step[axis];
+
+        self.next = GridRayStep {
+            cell,
+            distance: exits[axis],
+            face: Some(Direction::along(axis, self.step[axis] < 0)),
+        };
+        Some(result)
+    }
+}
+
 /// Utility trait that adds various iterators.
 pub trait VecIterators {
     /// Iterate an axis-aligned box defined by `self` (inclusive) and _end_ (exclusive).
@@ -113,3 +209,44 @@ impl VecIterators for UVec3 {
         Vec3BoxIter::new(*self, *end)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn grid_ray_visits_adjacent_cells_in_order() {
+        let direction = Vec3::new(1.0, 0.5, 0.0).normalize();
+        let steps: Vec<_> = GridRayIter::new(Vec3::new(0.2, 0.0, 0.0), direction)
+            .take(5)
+            .collect();
+        for pair in steps.windows(2) {
+            assert!(pair[1].distance >= pair[0].distance);
+            assert_eq!((pair[1].cell - pair[0].cell).abs().element_sum(), 1);
+        }
+
+        assert_eq!(steps[0].cell, IVec3::ZERO);
+        assert_eq!(steps[0].face, None);
+        assert_eq!(steps[1].cell, IVec3::new(1, 0, 0));
+        assert_eq!(steps[1].face, Some(Direction::West));
+        assert!((steps[1].distance - 0.3 / direction.x).abs() < 1e-5);
+    }
+
+    #[test]
+    fn grid_ray_along_an_axis() {
+        let steps: Vec<_> = GridRayIter::new(Vec3::ZERO, -Vec3::Z).take(3).collect();
+        assert_eq!(steps[2].cell, IVec3::new(0, 0, -2));
+        assert_eq!(steps[2].face, Some(Direction::Up));
+        assert_eq!(steps[2].distance, 1.5);
+
+        // Distances are not accumulated, so they stay exact
+        let step = GridRayIter::new(Vec3::ZERO, Vec3::X).nth(1000).unwrap();
+        assert_eq!(step.distance, 999.5);
+    }
+
+    #[test]
+    fn degenerate_grid_ray_visits_only_origin() {
+        assert_eq!(GridRayIter::new(Vec3::ZERO, Vec3::ZERO).count(), 1);
+        assert_eq!(GridRayIter::new(Vec3::ZERO, Vec3::NAN).count(), 1);
+    }
+}